@@ -0,0 +1,337 @@
+use std::{
+    cell::{Cell, RefCell},
+    ffi::{OsStr, OsString},
+    fs::{self, File},
+    io::{self, BufRead, BufWriter, Write},
+    mem,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::{expr::Entry, printf::{os_bytes, Format}};
+
+/// Stay well below the kernel limit on the size of a command line, like xargs
+const ARG_MAX: usize = 128 * 1024;
+
+/// `--print`, `--print0`, `--printf` and `--fprint`. Nothing more is written
+/// after an error, which is kept for `flush` to report.
+#[derive(Debug)]
+pub struct Print {
+    output: Output,
+    error: RefCell<Option<io::Error>>,
+}
+
+#[derive(Debug)]
+pub enum Output {
+    Line,
+    Null,
+    Format(Format),
+    File(RefCell<BufWriter<File>>),
+}
+
+impl Print {
+    pub fn new(output: Output) -> Self {
+        Print {
+            output,
+            error: RefCell::new(None),
+        }
+    }
+
+    /// `--fprint FILE` truncates FILE up front, even if nothing matches
+    pub fn to_file(path: &Path) -> Result<Self> {
+        let file = File::create(path).map_err(|e| anyhow!("{}: {e}", path.display()))?;
+        Ok(Print::new(Output::File(RefCell::new(BufWriter::new(file)))))
+    }
+
+    /// Write the entry, the paths as raw bytes so odd names survive
+    pub fn run(&self, entry: &Entry) -> bool {
+        if self.error.borrow().is_some() {
+            return true;
+        }
+        let path = &*os_bytes(entry.path().as_os_str());
+        let result = match &self.output {
+            Output::Line => io::stdout().lock().write_all(&[path, b"\n"].concat()),
+            Output::Null => io::stdout().lock().write_all(&[path, b"\0"].concat()),
+            Output::Format(format) => io::stdout().lock().write_all(&format.render(entry)),
+            Output::File(file) => file.borrow_mut().write_all(&[path, b"\n"].concat()),
+        };
+        if let Err(e) = result {
+            *self.error.borrow_mut() = Some(e);
+        }
+        true
+    }
+
+    /// Whether the reader went away, as `head` does once it has enough, so
+    /// there is no point going on
+    pub fn closed(&self) -> bool {
+        self.error
+            .borrow()
+            .as_ref()
+            .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+    }
+
+    /// Flush the output, failing with the first error writing it unless the
+    /// reader simply went away
+    pub fn flush(&self) -> Result<()> {
+        let result = match self.error.take() {
+            Some(e) => Err(e),
+            None => match &self.output {
+                Output::File(file) => file.borrow_mut().flush(),
+                _ => io::stdout().flush(),
+            },
+        };
+        match result {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// `--delete`: remove the entry, after everything below it as `--delete`
+/// turns on `--depth`. The search path itself and anything that resolves
+/// outside of it are left alone.
+#[derive(Debug)]
+pub struct Delete {
+    /// With --dry-run, where the paths that would be removed are printed
+    dry_run: Option<Print>,
+    /// The current search path and where it really is
+    root: RefCell<Option<(PathBuf, PathBuf)>>,
+    failed: Cell<bool>,
+}
+
+impl Delete {
+    /// With `dry_run`, only print the paths that would be removed
+    pub fn new(dry_run: bool) -> Self {
+        Delete {
+            dry_run: dry_run.then(|| Print::new(Output::Line)),
+            root: RefCell::new(None),
+            failed: Cell::new(false),
+        }
+    }
+
+    /// Remove the entry, true when it is gone. The search path itself is
+    /// quietly kept, as GNU find does with `.`.
+    pub fn run(&self, entry: &Entry) -> bool {
+        if entry.dir_entry.depth() == 0 {
+            return false;
+        }
+        let result = self.check(entry).and_then(|()| {
+            if let Some(print) = &self.dry_run {
+                print.run(entry);
+                return Ok(());
+            }
+            let path = entry.path();
+            // A link is removed itself, even when findr followed it
+            let is_dir = fs::symlink_metadata(path)?.is_dir();
+            if is_dir {
+                fs::remove_dir(path)?;
+            } else {
+                fs::remove_file(path)?;
+            }
+            Ok(())
+        });
+        match result {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("cannot delete {}: {e}", entry.path().display());
+                self.failed.set(true);
+                false
+            }
+        }
+    }
+
+    /// Whether an entry could not be removed
+    pub fn failed(&self) -> bool {
+        self.failed.get()
+    }
+
+    /// Whether the reader of the --dry-run paths went away
+    pub fn closed(&self) -> bool {
+        self.dry_run.as_ref().is_some_and(Print::closed)
+    }
+
+    pub fn flush(&self) -> Result<()> {
+        match &self.dry_run {
+            Some(print) => print.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Refuse anything whose directory is not inside of the search path once
+    /// links are resolved
+    fn check(&self, entry: &Entry) -> Result<()> {
+        let mut root = self.root.borrow_mut();
+        if root.as_ref().is_none_or(|(path, _)| path != entry.root()) {
+            *root = Some((entry.root().to_path_buf(), fs::canonicalize(entry.root())?));
+        }
+        let dir = fs::canonicalize(entry.path().parent().unwrap_or(Path::new(".")))?;
+        if !root.as_ref().is_some_and(|(_, real_root)| dir.starts_with(real_root)) {
+            bail!("refusing to delete outside of {}", entry.root().display());
+        }
+        Ok(())
+    }
+}
+
+/// `--exec`, `--execdir` and `--ok`: run a command where `{}` stands for the
+/// path of the entry. Commands ending in `{} +` collect many paths into one
+/// run instead of running once per entry.
+#[derive(Debug)]
+pub struct Exec {
+    command: Vec<String>,
+    batch: Option<RefCell<Batch>>,
+    in_dir: bool,
+    confirm: bool,
+    failed: Cell<bool>,
+}
+
+/// Paths waiting to be passed to a `{} +` command, all from the same
+/// directory for `--execdir`
+#[derive(Debug, Default)]
+struct Batch {
+    dir: Option<PathBuf>,
+    paths: Vec<OsString>,
+    size: usize,
+}
+
+impl Exec {
+    /// Build from the values of one `--exec` occurrence, without the `;`
+    pub fn new(mut command: Vec<String>, in_dir: bool, confirm: bool) -> Result<Self> {
+        let batched = command.ends_with(&["{}".to_string(), "+".to_string()]);
+        if batched {
+            if confirm {
+                bail!("--ok does not support \"{{}} +\"");
+            }
+            command.truncate(command.len() - 2);
+        }
+        if command.is_empty() {
+            bail!("missing command for --exec");
+        }
+        Ok(Exec {
+            command,
+            batch: batched.then(|| RefCell::new(Batch::default())),
+            in_dir,
+            confirm,
+            failed: Cell::new(false),
+        })
+    }
+
+    /// Run the command for a path, true when it exits successfully.
+    /// Batched commands always match, their paths are run later.
+    pub fn run(&self, path: &Path) -> bool {
+        let (dir, path) = self.locate(path);
+        match &self.batch {
+            None => {
+                let args: Vec<OsString> = self.command[1..]
+                    .iter()
+                    .map(|arg| replace_braces(arg, &path))
+                    .collect();
+                if self.confirm && !confirm(&self.command[0], &args) {
+                    return false;
+                }
+                self.spawn(dir.as_deref(), &args)
+            }
+            Some(batch) => {
+                let full = {
+                    let batch = batch.borrow();
+                    batch.dir != dir || batch.size + path.len() + 1 > ARG_MAX
+                };
+                if full {
+                    self.flush();
+                }
+                let mut batch = batch.borrow_mut();
+                batch.dir = dir;
+                batch.size += path.len() + 1;
+                batch.paths.push(path);
+                true
+            }
+        }
+    }
+
+    /// Run any paths still waiting in the batch
+    pub fn flush(&self) {
+        let Some(batch) = &self.batch else {
+            return;
+        };
+        let Batch { dir, paths, .. } = mem::take(&mut *batch.borrow_mut());
+        if paths.is_empty() {
+            return;
+        }
+        let args: Vec<OsString> = self.command[1..]
+            .iter()
+            .map(OsString::from)
+            .chain(paths)
+            .collect();
+        if !self.spawn(dir.as_deref(), &args) {
+            self.failed.set(true);
+        }
+    }
+
+    /// Whether a batched command exited unsuccessfully
+    pub fn failed(&self) -> bool {
+        self.failed.get()
+    }
+
+    /// The directory to run in and the path to pass to the command
+    fn locate(&self, path: &Path) -> (Option<PathBuf>, OsString) {
+        if !self.in_dir {
+            return (None, path.as_os_str().to_owned());
+        }
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        (
+            Some(dir.to_path_buf()),
+            Path::new(".").join(name).into_os_string(),
+        )
+    }
+
+    fn spawn(&self, dir: Option<&Path>, args: &[OsString]) -> bool {
+        let mut cmd = Command::new(&self.command[0]);
+        cmd.args(args);
+        if let Some(dir) = dir {
+            cmd.current_dir(dir);
+        }
+        match cmd.status() {
+            Ok(status) => status.success(),
+            Err(e) => {
+                eprintln!("{}: {e}", self.command[0]);
+                false
+            }
+        }
+    }
+}
+
+/// Replace every `{}` in an argument with the path
+fn replace_braces(arg: &str, path: &OsStr) -> OsString {
+    let mut result = OsString::new();
+    let mut parts = arg.split("{}");
+    if let Some(first) = parts.next() {
+        result.push(first);
+    }
+    for part in parts {
+        result.push(path);
+        result.push(part);
+    }
+    result
+}
+
+/// Ask on stderr whether to run the command, reading the answer from stdin
+fn confirm(program: &str, args: &[OsString]) -> bool {
+    let mut prompt = format!("< {program}");
+    for arg in args {
+        prompt.push(' ');
+        prompt.push_str(&arg.to_string_lossy());
+    }
+    eprint!("{prompt} > ? ");
+    let _ = io::stderr().flush();
+
+    let mut answer = String::new();
+    match io::stdin().lock().read_line(&mut answer) {
+        Ok(_) => answer.trim_start().starts_with(['y', 'Y']),
+        Err(_) => false,
+    }
+}
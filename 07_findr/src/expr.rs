@@ -0,0 +1,566 @@
+use std::{
+    cell::OnceCell,
+    ffi::OsString,
+    fs::{self, FileType, Metadata},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{bail, Result};
+use clap::ArgMatches;
+use globset::GlobMatcher;
+use regex::Regex;
+use ignore::DirEntry;
+
+use crate::{
+    action::{Delete, Exec, Output, Print},
+    predicate::{self, Age, Compare, Perm, Size, TimeField},
+    printf::Format,
+    EntryType,
+};
+
+/// A node of the expression tree built from the command line
+#[derive(Debug)]
+pub enum Expr {
+    True,
+    Name(GlobMatcher),
+    NameRegex(Regex),
+    Path(GlobMatcher),
+    Regex(Regex),
+    Type(EntryType),
+    XType(EntryType),
+    Size(Size),
+    Age(Age),
+    Newer(SystemTime),
+    Perm(Perm),
+    User(u32),
+    Group(u32),
+    NoUser,
+    NoGroup,
+    Empty,
+    Print(Print),
+    Exec(Exec),
+    Delete(Delete),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+/// A walked entry whose metadata is only read once a test asks for it
+pub struct Entry<'a> {
+    pub dir_entry: &'a DirEntry,
+    root: &'a Path,
+    metadata: OnceCell<Option<Metadata>>,
+}
+
+impl<'a> Entry<'a> {
+    /// An entry found under the search path `root`
+    pub fn new(dir_entry: &'a DirEntry, root: &'a Path) -> Self {
+        Entry {
+            dir_entry,
+            root,
+            metadata: OnceCell::new(),
+        }
+    }
+
+    /// The path of the entry, with the search path named as it was given
+    /// even when findr walked it through the link it points to
+    pub fn path(&self) -> &Path {
+        if self.dir_entry.depth() == 0 {
+            self.root
+        } else {
+            self.dir_entry.path()
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        self.root
+    }
+
+    pub fn metadata(&self) -> Option<&Metadata> {
+        self.metadata
+            .get_or_init(|| match self.dir_entry.metadata() {
+                Ok(metadata) => Some(metadata),
+                Err(e) => {
+                    eprintln!("{e}");
+                    None
+                }
+            })
+            .as_ref()
+    }
+
+    fn is_empty(&self) -> bool {
+        let Some(file_type) = self.dir_entry.file_type() else {
+            return false;
+        };
+        if file_type.is_dir() {
+            match fs::read_dir(self.path()) {
+                Ok(mut entries) => entries.next().is_none(),
+                Err(e) => {
+                    eprintln!("{}: {e}", self.path().display());
+                    false
+                }
+            }
+        } else {
+            file_type.is_file() && self.metadata().is_some_and(|m| m.len() == 0)
+        }
+    }
+
+    /// The type `--xtype` tests: what a symlink points to, or the link
+    /// itself when it was followed. A dangling link stays a link.
+    fn xtype(&self) -> Option<FileType> {
+        let file_type = self.dir_entry.file_type()?;
+        let metadata = if file_type.is_symlink() {
+            fs::metadata(self.path())
+        } else if self.dir_entry.path_is_symlink() {
+            fs::symlink_metadata(self.path())
+        } else {
+            return Some(file_type);
+        };
+        Some(metadata.map_or(file_type, |m| m.file_type()))
+    }
+}
+
+impl Expr {
+    /// Evaluate the expression for an entry, left to right with short-circuiting
+    pub fn eval(&self, entry: &Entry) -> bool {
+        match self {
+            Expr::True => true,
+            Expr::Name(glob) => glob.is_match(entry.dir_entry.file_name()),
+            Expr::NameRegex(re) => re.is_match(&entry.dir_entry.file_name().to_string_lossy()),
+            Expr::Path(glob) => glob.is_match(entry.path()),
+            Expr::Regex(re) => re.is_match(&entry.path().to_string_lossy()),
+            Expr::Type(entry_type) => entry_type.matches(entry.dir_entry.file_type()),
+            Expr::XType(entry_type) => entry_type.matches(entry.xtype()),
+            Expr::Size(size) => entry.metadata().is_some_and(|m| size.matches(m)),
+            Expr::Age(age) => entry.metadata().is_some_and(|m| age.matches(m)),
+            Expr::Newer(time) => entry
+                .metadata()
+                .and_then(|m| m.modified().ok())
+                .is_some_and(|modified| modified > *time),
+            Expr::Perm(perm) => entry.metadata().is_some_and(|m| perm.matches(m)),
+            Expr::User(uid) => entry.metadata().and_then(predicate::uid) == Some(*uid),
+            Expr::Group(gid) => entry.metadata().and_then(predicate::gid) == Some(*gid),
+            Expr::NoUser => entry.metadata().is_some_and(predicate::has_no_user),
+            Expr::NoGroup => entry.metadata().is_some_and(predicate::has_no_group),
+            Expr::Empty => entry.is_empty(),
+            Expr::Print(print) => print.run(entry),
+            Expr::Exec(exec) => exec.run(entry.path()),
+            Expr::Delete(delete) => delete.run(entry),
+            Expr::Not(expr) => !expr.eval(entry),
+            Expr::And(left, right) => left.eval(entry) && right.eval(entry),
+            Expr::Or(left, right) => left.eval(entry) || right.eval(entry),
+        }
+    }
+
+    /// Whether the expression prints or runs anything itself, otherwise
+    /// matching entries are printed
+    pub fn has_action(&self) -> bool {
+        match self {
+            Expr::Print(_) | Expr::Exec(_) | Expr::Delete(_) => true,
+            Expr::Not(expr) => expr.has_action(),
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                left.has_action() || right.has_action()
+            }
+            _ => false,
+        }
+    }
+
+    /// Run the `{} +` commands still waiting for paths and flush the output,
+    /// failing if any action did
+    pub fn finish(&self) -> Result<()> {
+        match self {
+            Expr::Print(print) => print.flush(),
+            Expr::Exec(exec) => {
+                exec.flush();
+                if exec.failed() {
+                    bail!("one or more commands failed");
+                }
+                Ok(())
+            }
+            Expr::Delete(delete) => {
+                delete.flush()?;
+                if delete.failed() {
+                    bail!("one or more entries could not be deleted");
+                }
+                Ok(())
+            }
+            Expr::Not(expr) => expr.finish(),
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                let left = left.finish();
+                let right = right.finish();
+                left.and(right)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the output of an action is gone, so that the walk can stop
+    pub fn closed(&self) -> bool {
+        match self {
+            Expr::Print(print) => print.closed(),
+            Expr::Delete(delete) => delete.closed(),
+            Expr::Not(expr) => expr.closed(),
+            Expr::And(left, right) | Expr::Or(left, right) => left.closed() || right.closed(),
+            _ => false,
+        }
+    }
+
+    /// OR together a list of tests, an empty list matches everything
+    fn any(exprs: Vec<Expr>) -> Expr {
+        exprs
+            .into_iter()
+            .reduce(|left, right| Expr::Or(Box::new(left), Box::new(right)))
+            .unwrap_or(Expr::True)
+    }
+}
+
+#[derive(Debug)]
+enum Token {
+    Test(Expr),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+/// Argument ids of the operator flags in `ExprArgs`
+const OPERATORS: [&str; 5] = ["and", "or", "not", "open", "close"];
+
+/// Argument ids of the tests and actions in `ExprArgs` that take no value
+const FLAG_TESTS: [&str; 5] = ["no_user", "no_group", "empty", "print", "print0"];
+
+impl Token {
+    fn flag(id: &str) -> Token {
+        match id {
+            "and" => Token::And,
+            "or" => Token::Or,
+            "not" => Token::Not,
+            "open" => Token::Open,
+            "close" => Token::Close,
+            "no_user" => Token::Test(Expr::NoUser),
+            "no_group" => Token::Test(Expr::NoGroup),
+            "empty" => Token::Test(Expr::Empty),
+            "print" => Token::Test(Expr::Print(Print::new(Output::Line))),
+            "print0" => Token::Test(Expr::Print(Print::new(Output::Null))),
+            _ => unreachable!("unknown flag {id}"),
+        }
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            Token::Test(_) => "a test",
+            Token::And => "-a",
+            Token::Or => "-o",
+            Token::Not => "!",
+            Token::Open => "(",
+            Token::Close => ")",
+        }
+    }
+}
+
+/// Rewrite the GNU spellings of the options into flags clap understands:
+/// `(`, `)`, `!` and single-dash long options like `-name` or `-exec`, which
+/// clap would otherwise read as `-n ame`. A command ending in `{} +` also
+/// gets the `;` clap needs to find its end, and one that never ends is an
+/// error rather than taking the rest of the arguments with it.
+pub fn normalize_args<I: IntoIterator<Item = OsString>>(
+    args: I,
+    longs: &[&str],
+) -> Result<Vec<OsString>> {
+    let mut normalized: Vec<OsString> = vec![];
+    let mut rest_are_paths = false;
+    // The action whose command is being read, until its `;` or `{} +`
+    let mut command: Option<String> = None;
+    for arg in args {
+        if rest_are_paths {
+            normalized.push(arg);
+            continue;
+        }
+        if command.is_some() {
+            let after_braces = normalized.last().is_some_and(|last| last == "{}");
+            match arg.to_str() {
+                Some(";") => command = None,
+                Some("+") if after_braces => {
+                    command = None;
+                    normalized.push(arg);
+                    normalized.push(";".into());
+                    continue;
+                }
+                _ => {}
+            }
+            normalized.push(arg);
+            continue;
+        }
+        let arg = match arg.to_str() {
+            Some("--") => {
+                rest_are_paths = true;
+                arg
+            }
+            Some("(") => "--(".into(),
+            Some(")") => "--)".into(),
+            Some("!") => "--not".into(),
+            Some(flag) if flag.len() > 2 && !flag.starts_with("--") => {
+                match flag.strip_prefix('-') {
+                    Some(long) if longs.contains(&long) => format!("--{long}").into(),
+                    _ => arg,
+                }
+            }
+            _ => arg,
+        };
+        if let Some(action @ ("--exec" | "--execdir" | "--ok")) = arg.to_str() {
+            command = Some(action.to_string());
+        }
+        normalized.push(arg);
+    }
+    match command {
+        Some(action) => bail!("missing argument to `{action}`"),
+        None => Ok(normalized),
+    }
+}
+
+/// Build the expression from the tests, actions and operators in the order
+/// they were given on the command line, with `-a` implied between two of
+/// them as in GNU find.
+///
+/// To keep the original behaviour of repeating `-n` or `-t`, a name or type
+/// test that directly follows another of the same kind is ORed with it
+/// instead. Matching entries are printed unless the expression has an action.
+pub fn parse(matches: &ArgMatches) -> Result<Expr> {
+    let now = SystemTime::now();
+    let age = |field, unit| {
+        move |compare: &Compare| {
+            Expr::Age(Age {
+                field,
+                compare: *compare,
+                unit,
+                now,
+            })
+        }
+    };
+
+    let mut tokens: Vec<(usize, Token)> = vec![];
+    let glob = |make: fn(GlobMatcher) -> Expr| move |glob: &GlobMatcher| make(glob.clone());
+    let regex = |make: fn(Regex) -> Expr| move |re: &Regex| make(re.clone());
+    tokens.extend(tests(matches, "names", glob(Expr::Name)));
+    tokens.extend(tests(matches, "inames", glob(Expr::Name)));
+    tokens.extend(tests(matches, "name_regexes", regex(Expr::NameRegex)));
+    tokens.extend(tests(matches, "path_globs", glob(Expr::Path)));
+    tokens.extend(tests(matches, "ipath_globs", glob(Expr::Path)));
+    tokens.extend(tests(matches, "regexes", regex(Expr::Regex)));
+    tokens.extend(tests(matches, "iregexes", regex(Expr::Regex)));
+    tokens.extend(tests(matches, "entry_types", |t: &EntryType| {
+        Expr::Type(t.clone())
+    }));
+    tokens.extend(tests(matches, "xtypes", |t: &EntryType| {
+        Expr::XType(t.clone())
+    }));
+    tokens.extend(tests(matches, "size", |size: &Size| Expr::Size(*size)));
+    tokens.extend(tests(matches, "mtime", age(TimeField::Modified, 86400)));
+    tokens.extend(tests(matches, "atime", age(TimeField::Accessed, 86400)));
+    tokens.extend(tests(matches, "ctime", age(TimeField::Changed, 86400)));
+    tokens.extend(tests(matches, "mmin", age(TimeField::Modified, 60)));
+    tokens.extend(tests(matches, "newer", |time: &SystemTime| {
+        Expr::Newer(*time)
+    }));
+    tokens.extend(tests(matches, "perm", |perm: &Perm| Expr::Perm(*perm)));
+    tokens.extend(tests(matches, "user", |uid: &u32| Expr::User(*uid)));
+    tokens.extend(tests(matches, "group", |gid: &u32| Expr::Group(*gid)));
+    tokens.extend(tests(matches, "printf", |format: &Format| {
+        Expr::Print(Print::new(Output::Format(format.clone())))
+    }));
+    tokens.extend(occurrences(matches, "fprint", |paths: Vec<&PathBuf>| {
+        Ok(Expr::Print(Print::to_file(paths[0])?))
+    })?);
+    for (id, in_dir, confirm) in [
+        ("exec", false, false),
+        ("exec_dir", true, false),
+        ("ok", false, true),
+    ] {
+        tokens.extend(occurrences(matches, id, |command: Vec<&String>| {
+            let command = command.into_iter().cloned().collect();
+            Ok(Expr::Exec(Exec::new(command, in_dir, confirm)?))
+        })?);
+    }
+
+    for id in OPERATORS {
+        for index in matches.indices_of(id).into_iter().flatten() {
+            tokens.push((index, Token::flag(id)));
+        }
+    }
+    for id in FLAG_TESTS {
+        for index in matches.indices_of(id).into_iter().flatten() {
+            tokens.push((index, Token::flag(id)));
+        }
+    }
+    for index in matches.indices_of("delete").into_iter().flatten() {
+        let delete = Delete::new(matches.get_flag("dry_run"));
+        tokens.push((index, Token::Test(Expr::Delete(delete))));
+    }
+
+    tokens.sort_by_key(|(index, _)| *index);
+    let expr = Parser::new(join_repeated(tokens.into_iter().map(|(_, token)| token))).parse()?;
+
+    if expr.has_action() {
+        Ok(expr)
+    } else {
+        Ok(Expr::And(
+            Box::new(expr),
+            Box::new(Expr::Print(Print::new(Output::Line))),
+        ))
+    }
+}
+
+/// The tests that are ORed together when repeated, like `-n a -n b`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Kind {
+    Name,
+    Type,
+}
+
+impl Kind {
+    fn of(token: &Token) -> Option<Kind> {
+        match token {
+            Token::Test(Expr::Name(_) | Expr::NameRegex(_)) => Some(Kind::Name),
+            Token::Test(Expr::Type(_)) => Some(Kind::Type),
+            _ => None,
+        }
+    }
+}
+
+/// OR each name or type test with the one right before it when that is of
+/// the same kind. A test that `!` applies to is left alone, and so is
+/// everything else, so nothing moves out of command-line order.
+fn join_repeated(tokens: impl IntoIterator<Item = Token>) -> Vec<Token> {
+    let mut joined: Vec<Token> = vec![];
+    // The kind of the last token, if another test of it can be joined on
+    let mut last_kind = None;
+    for token in tokens {
+        let kind = Kind::of(&token);
+        if kind.is_some() && kind == last_kind {
+            if let (Some(Token::Test(left)), Token::Test(right)) = (joined.pop(), token) {
+                joined.push(Token::Test(Expr::Or(Box::new(left), Box::new(right))));
+            }
+            continue;
+        }
+        let after_not = matches!(joined.last(), Some(Token::Not));
+        last_kind = if after_not { None } else { kind };
+        joined.push(token);
+    }
+    joined
+}
+
+/// One test per occurrence of the option `id`, with several values given to
+/// the same occurrence (`-t f l`) ORed together
+fn tests<T, F>(matches: &ArgMatches, id: &str, make: F) -> Vec<(usize, Token)>
+where
+    T: Clone + Send + Sync + 'static,
+    F: Fn(&T) -> Expr,
+{
+    occurrences(matches, id, |values: Vec<&T>| {
+        Ok(Expr::any(values.into_iter().map(&make).collect()))
+    })
+    .unwrap_or_default()
+}
+
+/// One token per occurrence of the option `id`, made from all of its values
+/// and placed at the position of the first one
+fn occurrences<T, F>(matches: &ArgMatches, id: &str, make: F) -> Result<Vec<(usize, Token)>>
+where
+    T: Clone + Send + Sync + 'static,
+    F: Fn(Vec<&T>) -> Result<Expr>,
+{
+    let (Some(occurrences), Some(mut indices)) =
+        (matches.get_occurrences::<T>(id), matches.indices_of(id))
+    else {
+        return Ok(vec![]);
+    };
+
+    let mut tokens = vec![];
+    for values in occurrences {
+        let values: Vec<&T> = values.collect();
+        if let Some(index) = indices.by_ref().take(values.len()).min() {
+            tokens.push((index, Token::Test(make(values)?)));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser for the GNU `find` grammar, from lowest to
+/// highest precedence: `-o`, `-a` (or juxtaposition), `!`, `( )`
+struct Parser {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    fn new(tokens: Vec<Token>) -> Self {
+        Parser {
+            tokens: tokens.into_iter().peekable(),
+        }
+    }
+
+    fn parse(mut self) -> Result<Expr> {
+        if self.tokens.peek().is_none() {
+            return Ok(Expr::True);
+        }
+        let expr = self.parse_or()?;
+        match self.tokens.next() {
+            None => Ok(expr),
+            Some(Token::Close) => bail!("unexpected ')' without a matching '('"),
+            Some(token) => bail!("unexpected {}", token.describe()),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while matches!(self.tokens.peek(), Some(Token::Or)) {
+            self.tokens.next();
+            let right = self.parse_and()?;
+            expr = Expr::Or(Box::new(expr), Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_not()?;
+        loop {
+            match self.tokens.peek() {
+                Some(Token::And) => {
+                    self.tokens.next();
+                }
+                Some(Token::Test(_) | Token::Not | Token::Open) => {}
+                _ => break,
+            }
+            let right = self.parse_not()?;
+            expr = Expr::And(Box::new(expr), Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr> {
+        if matches!(self.tokens.peek(), Some(Token::Not)) {
+            self.tokens.next();
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
+        match self.tokens.next() {
+            Some(Token::Test(expr)) => Ok(expr),
+            Some(Token::Open) => {
+                if matches!(self.tokens.peek(), Some(Token::Close)) {
+                    bail!("empty parentheses are not allowed");
+                }
+                let expr = self.parse_or()?;
+                match self.tokens.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => bail!("missing ')' to close '('"),
+                }
+            }
+            Some(token) => bail!("expected an expression before {}", token.describe()),
+            None => bail!("expected an expression at the end"),
+        }
+    }
+}
@@ -1,9 +1,25 @@
-use clap::{builder::PossibleValue, Arg, ArgAction, Command, Parser, ValueEnum};
+mod action;
+mod expr;
+mod pattern;
+mod predicate;
+mod printf;
+mod walk;
+
+use std::{
+    path::PathBuf,
+    time::SystemTime,
+};
+
+use clap::{Arg, ArgAction, Command, CommandFactory, FromArgMatches, Parser};
+use entry_type::EntryType;
+use expr::Expr;
+use globset::GlobMatcher;
+use predicate::{Compare, Perm, Size};
+use printf::Format;
 use regex::Regex;
-use walkdir::{DirEntry, WalkDir};
 use anyhow::Result;
 
-#[derive(Debug, Parser)]
+#[derive(Debug, Default, Parser)]
 #[command(author, version, about)]
 /// Rust version of `find`
 struct Args {
@@ -11,60 +27,229 @@ struct Args {
     #[arg(value_name = "PATH", default_value = ".")]
     paths: Vec<String>,
 
-    /// Names
-    #[arg(short('n'), long("name"), value_name = "NAME", value_parser(Regex::new), action(ArgAction::Append), num_args(0..))]
-    names: Vec<Regex>,
+    /// Descend at most LEVELS below the search paths
+    #[arg(long("max-depth"), value_name = "LEVELS")]
+    max_depth: Option<usize>,
 
-    /// Entry type
-    #[arg(short('t'), long("type"), value_name = "TYPE", value_parser(clap::value_parser!(EntryType)), action(ArgAction::Append), num_args(0..))]
-    entry_types: Vec<EntryType>,
-}
+    /// Skip entries less than LEVELS below the search paths
+    #[arg(long("min-depth"), value_name = "LEVELS")]
+    min_depth: Option<usize>,
+
+    /// Do not descend into directories whose name matches the shell pattern,
+    /// which are still listed
+    #[arg(long("prune"), value_name = "PATTERN", value_parser(pattern::glob), action(ArgAction::Append))]
+    prune: Vec<GlobMatcher>,
+
+    /// Skip entries matched by .gitignore, .ignore, .git/info/exclude and
+    /// the global git excludes file, and hidden entries unless --hidden
+    #[arg(long("respect-ignore"))]
+    respect_ignore: bool,
+
+    /// Never follow symbolic links (default)
+    #[arg(short('P'), overrides_with_all(["follow_roots", "follow"]))]
+    no_follow: bool,
+
+    /// Follow symbolic links given as search paths
+    #[arg(short('H'), overrides_with_all(["no_follow", "follow"]))]
+    follow_roots: bool,
+
+    /// Follow all symbolic links, reporting loops instead of walking them
+    #[arg(short('L'), overrides_with_all(["no_follow", "follow_roots"]))]
+    follow: bool,
+
+    /// Visit the contents of a directory before the directory itself
+    #[arg(long("depth"))]
+    depth: bool,
+
+    /// With --delete, print what would be deleted instead
+    #[arg(long("dry-run"))]
+    dry_run: bool,
+
+    /// Do not descend into directories on other filesystems
+    #[arg(long("xdev"))]
+    xdev: bool,
+
+    /// Walk hidden entries with --respect-ignore
+    #[arg(long("hidden"))]
+    hidden: bool,
+
+    /// Walk with N threads, printing entries as they are found, or one per
+    /// CPU with 0
+    #[arg(short('j'), long("jobs"), value_name = "N", default_value_t = 1)]
+    jobs: usize,
+
+    /// Print the entries of each search path sorted by name
+    #[arg(long("sort"))]
+    sort: bool,
 
-#[derive(Debug, Eq, PartialEq, Clone)]
-enum EntryType {
-    Dir,
-    File,
-    Link
+    #[command(flatten)]
+    expr: ExprArgs,
 }
 
-impl ValueEnum for EntryType {
-    fn value_variants<'a>() -> &'a [Self] {
-        &[EntryType::Dir, EntryType::File, EntryType::Link]
-    }
+// The tests and operators of the expression. The fields themselves are never
+// read, `expr::parse` takes the values back out of the `ArgMatches` so it can
+// put them in command-line order.
+#[allow(dead_code)]
+#[derive(Debug, Default, clap::Args)]
+struct ExprArgs {
+    /// Shell pattern matching the whole name
+    #[arg(short('n'), long("name"), value_name = "NAME", value_parser(pattern::glob), action(ArgAction::Append), num_args(1))]
+    names: Vec<GlobMatcher>,
 
-    fn to_possible_value<'a>(&self) -> Option<PossibleValue> {
-        Some(match self {
-            EntryType::Dir => PossibleValue::new("d"),
-            EntryType::File => PossibleValue::new("f"),
-            EntryType::Link => PossibleValue::new("l"),
-        })
-    }
+    /// Like --name, ignoring case
+    #[arg(long("iname"), value_name = "NAME", value_parser(pattern::iglob), action(ArgAction::Append), num_args(1))]
+    inames: Vec<GlobMatcher>,
+
+    /// Regex matching anywhere in the name
+    #[arg(long("name-regex"), value_name = "REGEX", value_parser(Regex::new), action(ArgAction::Append), num_args(1))]
+    name_regexes: Vec<Regex>,
+
+    /// Shell pattern matching the whole path
+    #[arg(long("path"), value_name = "PATTERN", value_parser(pattern::glob), action(ArgAction::Append))]
+    path_globs: Vec<GlobMatcher>,
+
+    /// Like --path, ignoring case
+    #[arg(long("ipath"), value_name = "PATTERN", value_parser(pattern::iglob), action(ArgAction::Append))]
+    ipath_globs: Vec<GlobMatcher>,
+
+    /// Regex matching the whole path
+    #[arg(long("regex"), value_name = "REGEX", value_parser(pattern::anchored_regex), action(ArgAction::Append))]
+    regexes: Vec<Regex>,
+
+    /// Like --regex, ignoring case
+    #[arg(long("iregex"), value_name = "REGEX", value_parser(pattern::anchored_iregex), action(ArgAction::Append))]
+    iregexes: Vec<Regex>,
+
+    /// Entry type, or several separated by commas
+    #[arg(short('t'), long("type"), value_name = "TYPE", value_parser(clap::value_parser!(EntryType)), action(ArgAction::Append), num_args(0..), value_delimiter(','))]
+    entry_types: Vec<EntryType>,
+
+    /// Like --type, for the target of a symbolic link, or the link itself
+    /// when following links
+    #[arg(long("xtype"), value_name = "TYPE", value_parser(clap::value_parser!(EntryType)), action(ArgAction::Append), value_delimiter(','))]
+    xtypes: Vec<EntryType>,
+
+    /// Size in 512-byte blocks, or with a c, k, M or G suffix
+    #[arg(long("size"), value_name = "[+-]N[ckMG]", value_parser(Size::parse), action(ArgAction::Append), allow_hyphen_values(true))]
+    size: Vec<Size>,
+
+    /// Modified N days ago
+    #[arg(long("mtime"), value_name = "[+-]N", value_parser(Compare::parse), action(ArgAction::Append), allow_hyphen_values(true))]
+    mtime: Vec<Compare>,
+
+    /// Accessed N days ago
+    #[arg(long("atime"), value_name = "[+-]N", value_parser(Compare::parse), action(ArgAction::Append), allow_hyphen_values(true))]
+    atime: Vec<Compare>,
+
+    /// Status changed N days ago
+    #[arg(long("ctime"), value_name = "[+-]N", value_parser(Compare::parse), action(ArgAction::Append), allow_hyphen_values(true))]
+    ctime: Vec<Compare>,
+
+    /// Modified N minutes ago
+    #[arg(long("mmin"), value_name = "[+-]N", value_parser(Compare::parse), action(ArgAction::Append), allow_hyphen_values(true))]
+    mmin: Vec<Compare>,
+
+    /// Modified more recently than FILE
+    #[arg(long("newer"), value_name = "FILE", value_parser(predicate::modified_time), action(ArgAction::Append))]
+    newer: Vec<SystemTime>,
+
+    /// Permission bits are exactly MODE, all of -MODE or any of /MODE
+    #[arg(long("perm"), value_name = "MODE", value_parser(Perm::parse), action(ArgAction::Append), allow_hyphen_values(true))]
+    perm: Vec<Perm>,
+
+    /// Owned by user name or uid
+    #[arg(long("user"), value_name = "USER", value_parser(predicate::parse_user), action(ArgAction::Append))]
+    user: Vec<u32>,
+
+    /// Owned by group name or gid
+    #[arg(long("group"), value_name = "GROUP", value_parser(predicate::parse_group), action(ArgAction::Append))]
+    group: Vec<u32>,
+
+    /// Owner uid has no user
+    #[arg(long("nouser"), action(ArgAction::Append), num_args(0), default_missing_value("true"))]
+    no_user: Vec<bool>,
+
+    /// Owner gid has no group
+    #[arg(long("nogroup"), action(ArgAction::Append), num_args(0), default_missing_value("true"))]
+    no_group: Vec<bool>,
+
+    /// Empty file or directory
+    #[arg(long("empty"), action(ArgAction::Append), num_args(0), default_missing_value("true"))]
+    empty: Vec<bool>,
+
+    /// Print the path
+    #[arg(long("print"), action(ArgAction::Append), num_args(0), default_missing_value("true"))]
+    print: Vec<bool>,
+
+    /// Print the path followed by a null character
+    #[arg(long("print0"), action(ArgAction::Append), num_args(0), default_missing_value("true"))]
+    print0: Vec<bool>,
+
+    /// Print FORMAT, with directives %p %f %h %s %m %M %u %g %t %T@ %Tk %y %d
+    #[arg(long("printf"), value_name = "FORMAT", value_parser(Format::parse), action(ArgAction::Append), allow_hyphen_values(true))]
+    printf: Vec<Format>,
+
+    /// Print the path to FILE, which is truncated first
+    #[arg(long("fprint"), value_name = "FILE", action(ArgAction::Append))]
+    fprint: Vec<PathBuf>,
+
+    /// Delete the entry, implies --depth
+    #[arg(long("delete"), action(ArgAction::Append), num_args(0), default_missing_value("true"))]
+    delete: Vec<bool>,
+
+    /// Run COMMAND, `{}` is replaced by the path. End it with `;`, or with
+    /// `{} +` to pass as many paths as possible to each run
+    #[arg(long("exec"), value_name = "COMMAND", action(ArgAction::Append), num_args(1..), value_terminator(";"), allow_hyphen_values(true))]
+    exec: Vec<String>,
+
+    /// Like --exec, but run from the directory of the entry
+    #[arg(long("execdir"), value_name = "COMMAND", action(ArgAction::Append), num_args(1..), value_terminator(";"), allow_hyphen_values(true))]
+    exec_dir: Vec<String>,
+
+    /// Like --exec, but ask before running COMMAND
+    #[arg(long("ok"), value_name = "COMMAND", action(ArgAction::Append), num_args(1..), value_terminator(";"), allow_hyphen_values(true))]
+    ok: Vec<String>,
+
+    /// Match when both sides match (implied between two tests)
+    #[arg(short('a'), long("and"), action(ArgAction::Append), num_args(0), default_missing_value("true"))]
+    and: Vec<bool>,
+
+    /// Match when either side matches
+    #[arg(short('o'), long("or"), action(ArgAction::Append), num_args(0), default_missing_value("true"))]
+    or: Vec<bool>,
+
+    /// Negate the following test, also written `!`
+    #[arg(long("not"), action(ArgAction::Append), num_args(0), default_missing_value("true"))]
+    not: Vec<bool>,
+
+    /// Start a group, written `(`
+    #[arg(long("("), action(ArgAction::Append), num_args(0), default_missing_value("true"), hide(true))]
+    open: Vec<bool>,
+
+    /// End a group, written `)`
+    #[arg(long(")"), action(ArgAction::Append), num_args(0), default_missing_value("true"), hide(true))]
+    close: Vec<bool>,
 }
 
 
 fn main() {
-    if let Err(e) = run(Args::parse()) {
+    let command = Args::command();
+    let longs: Vec<&str> = command.get_arguments().filter_map(|arg| arg.get_long()).collect();
+    let args = expr::normalize_args(std::env::args_os(), &longs).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+    let matches = command.clone().get_matches_from(args);
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    // A directory can only be deleted once its contents are gone
+    args.depth |= matches.contains_id("delete");
+    if let Err(e) = expr::parse(&matches).and_then(|expr| run(args, expr)) {
         eprintln!("{e}");
         std::process::exit(1);
     }
 }
 
-fn run(args: Args) -> Result<()> {
-
-    let type_filter = |entry: &DirEntry| {
-        args.entry_types.is_empty() || args.entry_types.iter().any(|e| {
-            match e {
-                EntryType::Dir => entry.file_type().is_dir(),
-                EntryType::File => entry.file_type().is_file(),
-                EntryType::Link => entry.file_type().is_symlink()
-            }
-        })
-    };
-
-    let name_filter = |entry: &DirEntry| {
-        args.names.is_empty() || args.names.iter().any(|re| re.is_match(&entry.file_name().to_string_lossy()))
-    };
-
+fn run(args: Args, expr: Expr) -> Result<()> {
     for path in &args.paths {
         // for entry in WalkDir::new(path) {
         //     match entry {
@@ -85,25 +270,16 @@ fn run(args: Args) -> Result<()> {
         //     }
         // }
 
-        let entries = WalkDir::new(path)
-            .into_iter()
-            .filter_map(|entry| {
-                match entry {
-                    Err(e) => {
-                        eprintln!("{e}");
-                        None
-                    },
-                    Ok(e) => Some(e)
-                }
-            })
-            .filter(type_filter)
-            .filter(name_filter)
-            .map(|entry| entry.path().display().to_string())
-            .collect::<Vec<_>>();
-    
-        println!("{}", entries.join("\n"));
+        // Once stdout is closed, nothing more can be shown
+        if !walk::walk(&args, path, |entry| {
+            expr.eval(entry);
+            !expr.closed()
+        }) {
+            break;
+        }
     }
-    Ok(())
+
+    expr.finish()
 }
 
 fn _get_args() -> Args {
@@ -124,9 +300,9 @@ fn _get_args() -> Args {
                 .short('n')
                 .long("name")
                 .help("Name")
-                .value_parser(Regex::new)
+                .value_parser(pattern::glob)
                 .action(ArgAction::Append)
-                .num_args(0..),
+                .num_args(1),
         )
         .arg(
             Arg::new("types")
@@ -141,16 +317,21 @@ fn _get_args() -> Args {
         .get_matches();
     Args {
         paths: matches.get_many("paths").unwrap().cloned().collect(),
-        names: matches
-            .get_many("names")
-            .unwrap_or_default()
-            .cloned()
-            .collect(),
-        entry_types: matches
-            .get_many("types")
-            .unwrap_or_default()
-            .cloned()
-            .collect(),
+        jobs: 1,
+        expr: ExprArgs {
+            names: matches
+                .get_many("names")
+                .unwrap_or_default()
+                .cloned()
+                .collect(),
+            entry_types: matches
+                .get_many("types")
+                .unwrap_or_default()
+                .cloned()
+                .collect(),
+            ..Default::default()
+        },
+        ..Default::default()
     }
 }
 
@@ -0,0 +1,38 @@
+use anyhow::Result;
+use globset::{GlobBuilder, GlobMatcher};
+use regex::{Regex, RegexBuilder};
+
+/// A shell glob like `*.rs` that has to match the whole name or path.
+/// As with GNU find, `*` also matches `/` and a leading `.`
+pub fn glob(pattern: &str) -> Result<GlobMatcher> {
+    build_glob(pattern, false)
+}
+
+/// A case-insensitive [`glob`]
+pub fn iglob(pattern: &str) -> Result<GlobMatcher> {
+    build_glob(pattern, true)
+}
+
+fn build_glob(pattern: &str, case_insensitive: bool) -> Result<GlobMatcher> {
+    Ok(GlobBuilder::new(pattern)
+        .case_insensitive(case_insensitive)
+        .backslash_escape(true)
+        .build()?
+        .compile_matcher())
+}
+
+/// A regex that has to match the whole path, not just part of it
+pub fn anchored_regex(pattern: &str) -> Result<Regex> {
+    build_regex(pattern, false)
+}
+
+/// A case-insensitive [`anchored_regex`]
+pub fn anchored_iregex(pattern: &str) -> Result<Regex> {
+    build_regex(pattern, true)
+}
+
+fn build_regex(pattern: &str, case_insensitive: bool) -> Result<Regex> {
+    Ok(RegexBuilder::new(&format!("^(?:{pattern})$"))
+        .case_insensitive(case_insensitive)
+        .build()?)
+}
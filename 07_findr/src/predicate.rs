@@ -0,0 +1,243 @@
+use std::{
+    cmp::Ordering,
+    fs::{self, Metadata},
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, bail, Result};
+
+/// A numeric argument like `+5`, `-5` or `5`: more than, less than or exactly N
+#[derive(Debug, Clone, Copy)]
+pub struct Compare {
+    ordering: Ordering,
+    n: u64,
+}
+
+impl Compare {
+    pub fn parse(value: &str) -> Result<Self> {
+        let (ordering, digits) = match value.as_bytes().first() {
+            Some(b'+') => (Ordering::Greater, &value[1..]),
+            Some(b'-') => (Ordering::Less, &value[1..]),
+            _ => (Ordering::Equal, value),
+        };
+        let n = digits
+            .parse()
+            .map_err(|_| anyhow!("invalid number \"{value}\""))?;
+        Ok(Compare { ordering, n })
+    }
+
+    pub fn matches(&self, value: u64) -> bool {
+        value.cmp(&self.n) == self.ordering
+    }
+}
+
+/// `--size [+-]N[ckMG]`, counted in 512-byte blocks without a suffix
+#[derive(Debug, Clone, Copy)]
+pub struct Size {
+    compare: Compare,
+    unit: u64,
+}
+
+impl Size {
+    pub fn parse(value: &str) -> Result<Self> {
+        let (number, unit) = match value.chars().last() {
+            Some('c') => (&value[..value.len() - 1], 1),
+            Some('k') => (&value[..value.len() - 1], 1024),
+            Some('M') => (&value[..value.len() - 1], 1024 * 1024),
+            Some('G') => (&value[..value.len() - 1], 1024 * 1024 * 1024),
+            Some('b') => (&value[..value.len() - 1], 512),
+            _ => (value, 512),
+        };
+        let compare = Compare::parse(number).map_err(|_| anyhow!("invalid size \"{value}\""))?;
+        Ok(Size { compare, unit })
+    }
+
+    /// Like GNU find, the size is rounded up to a whole number of units
+    pub fn matches(&self, metadata: &Metadata) -> bool {
+        self.compare.matches(metadata.len().div_ceil(self.unit))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum TimeField {
+    Accessed,
+    Changed,
+    Modified,
+}
+
+/// `--mtime`, `--atime`, `--ctime` and `--mmin`: the age of a timestamp in
+/// whole days or minutes, compared to the time findr started
+#[derive(Debug, Clone, Copy)]
+pub struct Age {
+    pub field: TimeField,
+    pub compare: Compare,
+    pub unit: u64,
+    pub now: SystemTime,
+}
+
+impl Age {
+    pub fn matches(&self, metadata: &Metadata) -> bool {
+        let time = match self.field {
+            TimeField::Accessed => metadata.accessed().ok(),
+            TimeField::Changed => changed(metadata),
+            TimeField::Modified => metadata.modified().ok(),
+        };
+        let seconds = |time: SystemTime| match time.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(d) => d.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs() as i64),
+        };
+        let now = seconds(self.now);
+        let age = (now - time.map_or(0, seconds)).max(0) as u64;
+        self.compare.matches(age / self.unit)
+    }
+}
+
+/// When the inode last changed, for `--ctime`
+#[cfg(unix)]
+fn changed(metadata: &Metadata) -> Option<SystemTime> {
+    use std::{os::unix::fs::MetadataExt, time::Duration};
+    let since_epoch = Duration::new(metadata.ctime().max(0) as u64, metadata.ctime_nsec() as u32);
+    Some(SystemTime::UNIX_EPOCH + since_epoch)
+}
+
+/// Other systems keep no change time, so the last modification stands in
+#[cfg(not(unix))]
+fn changed(metadata: &Metadata) -> Option<SystemTime> {
+    metadata.modified().ok()
+}
+
+/// `--newer FILE`, read once when the arguments are parsed
+pub fn modified_time(path: &str) -> Result<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| anyhow!("{path}: {e}"))
+}
+
+/// `--perm MODE` matches the exact mode, `-MODE` needs all of the bits set
+/// and `/MODE` any of them
+#[derive(Debug, Clone, Copy)]
+pub enum Perm {
+    Exact(u32),
+    All(u32),
+    Any(u32),
+}
+
+impl Perm {
+    pub fn parse(value: &str) -> Result<Self> {
+        let mode = |digits: &str| {
+            u32::from_str_radix(digits, 8)
+                .ok()
+                .filter(|mode| *mode <= 0o7777)
+                .ok_or_else(|| anyhow!("invalid mode \"{value}\""))
+        };
+        Ok(match value.as_bytes().first() {
+            Some(b'-') => Perm::All(mode(&value[1..])?),
+            Some(b'/') => Perm::Any(mode(&value[1..])?),
+            _ => Perm::Exact(mode(value)?),
+        })
+    }
+
+    pub fn matches(&self, metadata: &Metadata) -> bool {
+        let mode = mode(metadata) & 0o7777;
+        match *self {
+            Perm::Exact(bits) => mode == bits,
+            Perm::All(bits) => mode & bits == bits,
+            Perm::Any(bits) => bits == 0 || mode & bits != 0,
+        }
+    }
+}
+
+/// The permission bits and file type of an entry
+#[cfg(unix)]
+pub fn mode(metadata: &Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode()
+}
+
+/// Without Unix modes, everything can be read and only what is not read-only
+/// written, with directories searchable
+#[cfg(not(unix))]
+pub fn mode(metadata: &Metadata) -> u32 {
+    let read = if metadata.is_dir() { 0o555 } else { 0o444 };
+    match metadata.permissions().readonly() {
+        true => read,
+        false => read | 0o222,
+    }
+}
+
+/// A user name or numeric uid
+#[cfg(unix)]
+pub fn parse_user(value: &str) -> Result<u32> {
+    match users::get_user_by_name(value) {
+        Some(user) => Ok(user.uid()),
+        None => match value.parse() {
+            Ok(uid) => Ok(uid),
+            Err(_) => bail!("\"{value}\" is not the name of a known user"),
+        },
+    }
+}
+
+/// A group name or numeric gid
+#[cfg(unix)]
+pub fn parse_group(value: &str) -> Result<u32> {
+    match users::get_group_by_name(value) {
+        Some(group) => Ok(group.gid()),
+        None => match value.parse() {
+            Ok(gid) => Ok(gid),
+            Err(_) => bail!("\"{value}\" is not the name of a known group"),
+        },
+    }
+}
+
+#[cfg(not(unix))]
+pub fn parse_user(_value: &str) -> Result<u32> {
+    bail!("--user is only supported on Unix")
+}
+
+#[cfg(not(unix))]
+pub fn parse_group(_value: &str) -> Result<u32> {
+    bail!("--group is only supported on Unix")
+}
+
+#[cfg(unix)]
+pub fn uid(metadata: &Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.uid())
+}
+
+#[cfg(unix)]
+pub fn gid(metadata: &Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.gid())
+}
+
+#[cfg(unix)]
+pub fn has_no_user(metadata: &Metadata) -> bool {
+    uid(metadata).is_some_and(|uid| users::get_user_by_uid(uid).is_none())
+}
+
+#[cfg(unix)]
+pub fn has_no_group(metadata: &Metadata) -> bool {
+    gid(metadata).is_some_and(|gid| users::get_group_by_gid(gid).is_none())
+}
+
+/// Files have no owner or group outside of Unix
+#[cfg(not(unix))]
+pub fn uid(_metadata: &Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(not(unix))]
+pub fn gid(_metadata: &Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(not(unix))]
+pub fn has_no_user(_metadata: &Metadata) -> bool {
+    false
+}
+
+#[cfg(not(unix))]
+pub fn has_no_group(_metadata: &Metadata) -> bool {
+    false
+}
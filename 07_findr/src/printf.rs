@@ -0,0 +1,220 @@
+use std::{
+    borrow::Cow,
+    ffi::OsStr,
+    fs::{FileType, Metadata},
+    path::Path,
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Local,
+};
+use crate::{expr::Entry, predicate, EntryType};
+
+/// A `--printf` format, parsed once from the command line
+#[derive(Debug, Clone)]
+pub struct Format(Vec<Piece>);
+
+#[derive(Debug, Clone)]
+enum Piece {
+    Text(Vec<u8>),
+    Directive(char),
+    /// `%Tk`: the modification time formatted with the strftime field `k`,
+    /// or seconds since the epoch for `%T@`
+    Time(char),
+}
+
+impl Format {
+    pub fn parse(format: &str) -> Result<Self> {
+        let mut pieces = vec![];
+        let mut text = vec![];
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('n') => text.push(b'\n'),
+                    Some('t') => text.push(b'\t'),
+                    Some('r') => text.push(b'\r'),
+                    Some('0') => text.push(b'\0'),
+                    Some('\\') => text.push(b'\\'),
+                    Some(other) => bail!("unknown escape \"\\{other}\" in --printf"),
+                    None => text.push(b'\\'),
+                },
+                '%' => {
+                    let directive = chars
+                        .next()
+                        .ok_or_else(|| anyhow!("missing directive after \"%\" in --printf"))?;
+                    let piece = match directive {
+                        '%' => {
+                            text.push(b'%');
+                            continue;
+                        }
+                        'p' | 'f' | 'h' | 's' | 'm' | 'M' | 'u' | 'g' | 't' | 'y' | 'd' => {
+                            Piece::Directive(directive)
+                        }
+                        'T' => match chars.next() {
+                            Some('@') => Piece::Time('@'),
+                            Some(field) if is_strftime_field(field) => Piece::Time(field),
+                            Some(field) => {
+                                bail!("unknown time field \"%T{field}\" in --printf")
+                            }
+                            None => bail!("missing time field after \"%T\" in --printf"),
+                        },
+                        other => bail!("unknown directive \"%{other}\" in --printf"),
+                    };
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+                    pieces.push(piece);
+                }
+                _ => {
+                    let mut buf = [0; 4];
+                    text.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+            }
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        Ok(Format(pieces))
+    }
+
+    /// Fill in the directives for an entry, reading its metadata only if a
+    /// directive needs it
+    pub fn render(&self, entry: &Entry) -> Vec<u8> {
+        let path = entry.path();
+        let mut out = vec![];
+        for piece in &self.0 {
+            match piece {
+                Piece::Text(text) => out.extend_from_slice(text),
+                Piece::Directive('p') => out.extend_from_slice(&os_bytes(path.as_os_str())),
+                Piece::Directive('f') => {
+                    let name = path.file_name().unwrap_or(path.as_os_str());
+                    out.extend_from_slice(&os_bytes(name));
+                }
+                Piece::Directive('h') => {
+                    let dir = match path.parent() {
+                        Some(parent) if !parent.as_os_str().is_empty() => parent,
+                        _ => Path::new("."),
+                    };
+                    out.extend_from_slice(&os_bytes(dir.as_os_str()));
+                }
+                Piece::Directive('d') => {
+                    out.extend_from_slice(entry.dir_entry.depth().to_string().as_bytes())
+                }
+                Piece::Directive('y') => out.push(type_char(entry.dir_entry.file_type()) as u8),
+                Piece::Directive(directive) => {
+                    if let Some(metadata) = entry.metadata() {
+                        let value = match directive {
+                            's' => metadata.len().to_string(),
+                            'm' => format!("{:o}", predicate::mode(metadata) & 0o7777),
+                            'M' => match type_char(entry.dir_entry.file_type()) {
+                                'f' => format!("-{}", format_mode(predicate::mode(metadata))),
+                                c => format!("{c}{}", format_mode(predicate::mode(metadata))),
+                            },
+                            'u' => owner(metadata),
+                            'g' => group(metadata),
+                            't' => modified(metadata)
+                                .format("%a %b %e %H:%M:%S %Y")
+                                .to_string(),
+                            _ => unreachable!("unknown directive {directive}"),
+                        };
+                        out.extend_from_slice(value.as_bytes());
+                    }
+                }
+                Piece::Time(field) => {
+                    if let Some(metadata) = entry.metadata() {
+                        let value = if *field == '@' {
+                            let since_epoch = metadata
+                                .modified()
+                                .ok()
+                                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                                .unwrap_or_default();
+                            format!("{}.{:09}", since_epoch.as_secs(), since_epoch.subsec_nanos())
+                        } else {
+                            modified(metadata).format(&format!("%{field}")).to_string()
+                        };
+                        out.extend_from_slice(value.as_bytes());
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+fn is_strftime_field(field: char) -> bool {
+    let spec = format!("%{field}");
+    field.is_ascii_alphabetic()
+        && StrftimeItems::new(&spec).all(|item| !matches!(item, Item::Error))
+}
+
+fn modified(metadata: &Metadata) -> DateTime<Local> {
+    DateTime::from(metadata.modified().unwrap_or(UNIX_EPOCH))
+}
+
+/// The raw bytes of a path or name on Unix, where they need not be UTF-8,
+/// and its UTF-8 text elsewhere
+#[cfg(unix)]
+pub fn os_bytes(s: &OsStr) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    Cow::Borrowed(s.as_bytes())
+}
+
+#[cfg(not(unix))]
+pub fn os_bytes(s: &OsStr) -> Cow<'_, [u8]> {
+    match s.to_string_lossy() {
+        Cow::Borrowed(s) => Cow::Borrowed(s.as_bytes()),
+        Cow::Owned(s) => Cow::Owned(s.into_bytes()),
+    }
+}
+
+/// `%u`: the name of the owner, or its uid when it has none
+#[cfg(unix)]
+fn owner(metadata: &Metadata) -> String {
+    use std::os::unix::fs::MetadataExt;
+    users::get_user_by_uid(metadata.uid())
+        .map(|u| u.name().to_string_lossy().into_owned())
+        .unwrap_or_else(|| metadata.uid().to_string())
+}
+
+/// `%g`: the name of the group, or its gid when it has none
+#[cfg(unix)]
+fn group(metadata: &Metadata) -> String {
+    use std::os::unix::fs::MetadataExt;
+    users::get_group_by_gid(metadata.gid())
+        .map(|g| g.name().to_string_lossy().into_owned())
+        .unwrap_or_else(|| metadata.gid().to_string())
+}
+
+/// Files have no owner or group to show outside of Unix
+#[cfg(not(unix))]
+fn owner(_metadata: &Metadata) -> String {
+    String::new()
+}
+
+#[cfg(not(unix))]
+fn group(_metadata: &Metadata) -> String {
+    String::new()
+}
+
+/// The letter `--type` uses for a file type, `U` when it is unknown
+fn type_char(file_type: Option<FileType>) -> char {
+    file_type
+        .and_then(|t| EntryType::of(&t))
+        .map_or('U', |t| t.letter())
+}
+
+/// Given a file mode in octal format like 0o751,
+/// return a string like "rwxr-x--x"
+fn format_mode(mode: u32) -> String {
+    [
+        0o400, 0o200, 0o100, 0o040, 0o020, 0o010, 0o004, 0o002, 0o001,
+    ]
+    .iter()
+    .zip("rwxrwxrwx".chars())
+    .map(|(mask, c)| if mode & mask == 0 { '-' } else { c })
+    .collect()
+}
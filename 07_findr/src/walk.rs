@@ -0,0 +1,154 @@
+use std::{cell::Cell, fs, path::Path, sync::mpsc, thread};
+
+use globset::GlobMatcher;
+use ignore::{DirEntry, WalkBuilder, WalkState};
+
+use crate::{expr::Entry, Args};
+
+/// Walk one search path and hand every entry to `visit`, as soon as it is
+/// found unless `--sort` has to see them all first. Errors are reported on
+/// stderr and the walk goes on, including the loops found when following
+/// symbolic links. The walk stops once `visit` returns false, and so does
+/// this.
+pub fn walk(args: &Args, path: &str, mut visit: impl FnMut(&Entry) -> bool) -> bool {
+    let root = Path::new(path);
+    let root_is_link = fs::symlink_metadata(root).is_ok_and(|m| m.file_type().is_symlink());
+    let follow_root = root_is_link && (args.follow_roots || args.follow);
+
+    let min_depth = args.min_depth.unwrap_or(0);
+    let stopped = Cell::new(false);
+    let mut visit = |entry: &DirEntry| {
+        if !stopped.get() && entry.depth() >= min_depth && !visit(&Entry::new(entry, root)) {
+            stopped.set(true);
+        }
+    };
+    let mut post_order = args.depth.then(PostOrder::default);
+    let mut emit = |entry: DirEntry| {
+        match &mut post_order {
+            Some(post_order) => post_order.push(entry, &mut visit),
+            None => visit(&entry),
+        }
+        !stopped.get()
+    };
+
+    // The kernel resolves a link named with a trailing slash, so walking
+    // "link/" descends into it while the entries below keep its name
+    let mut builder = WalkBuilder::new(if follow_root {
+        format!("{path}/")
+    } else {
+        path.to_string()
+    });
+    builder
+        .standard_filters(args.respect_ignore)
+        .hidden(args.respect_ignore && !args.hidden)
+        .follow_links(args.follow)
+        .same_file_system(args.xdev)
+        .max_depth(if root_is_link && !follow_root {
+            Some(0)
+        } else {
+            args.max_depth
+        })
+        .filter_entry({
+            let prune = args.prune.clone();
+            move |entry| !in_pruned(&prune, entry)
+        });
+
+    if args.jobs == 1 {
+        if args.sort {
+            builder.sort_by_file_name(|a, b| a.cmp(b));
+        }
+        for entry in builder.build() {
+            match entry {
+                Err(e) => eprintln!("{e}"),
+                Ok(entry) => {
+                    if !emit(entry) {
+                        break;
+                    }
+                }
+            }
+        }
+    } else {
+        walk_parallel(args, builder, emit);
+    }
+
+    if let Some(mut post_order) = post_order {
+        post_order.pop_to(0, &mut visit);
+    }
+    !stopped.get()
+}
+
+/// Walk with a pool of threads. The expression is not thread-safe, so the
+/// threads only read the directories and send the entries back here, and
+/// give up once this stops listening.
+fn walk_parallel(args: &Args, mut builder: WalkBuilder, mut emit: impl FnMut(DirEntry) -> bool) {
+    let (tx, rx) = mpsc::channel();
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            builder.threads(args.jobs).build_parallel().run(|| {
+                let tx = tx.clone();
+                Box::new(move |entry| match tx.send(entry) {
+                    Ok(()) => WalkState::Continue,
+                    Err(_) => WalkState::Quit,
+                })
+            });
+            drop(tx);
+        });
+
+        // --depth needs every directory to come before its contents, which
+        // only sorting restores
+        let mut sorted = vec![];
+        for entry in rx {
+            match entry {
+                Err(e) => eprintln!("{e}"),
+                Ok(entry) if args.sort || args.depth => sorted.push(entry),
+                Ok(entry) => {
+                    if !emit(entry) {
+                        return;
+                    }
+                }
+            }
+        }
+        // Paths compare component by component, so each directory is still
+        // followed by its own contents
+        sorted.sort_by(|a, b| a.path().cmp(b.path()));
+        sorted.into_iter().all(emit);
+    });
+}
+
+/// Holds each directory back until everything below it has been visited,
+/// for `--depth`. The entries have to come with every directory before its
+/// contents.
+#[derive(Default)]
+struct PostOrder(Vec<DirEntry>);
+
+impl PostOrder {
+    fn push(&mut self, entry: DirEntry, visit: &mut impl FnMut(&DirEntry)) {
+        self.pop_to(entry.depth(), visit);
+        if entry.file_type().is_some_and(|t| t.is_dir()) {
+            self.0.push(entry);
+        } else {
+            visit(&entry);
+        }
+    }
+
+    /// Visit the directories held at `depth` or deeper, as all of their
+    /// contents have been seen
+    fn pop_to(&mut self, depth: usize, visit: &mut impl FnMut(&DirEntry)) {
+        while self.0.last().is_some_and(|dir| dir.depth() >= depth) {
+            if let Some(dir) = self.0.pop() {
+                visit(&dir);
+            }
+        }
+    }
+}
+
+/// Whether the entry is in a directory that `--prune` keeps findr out of.
+/// Like GNU find, the directory itself is still listed.
+fn in_pruned(prune: &[GlobMatcher], entry: &DirEntry) -> bool {
+    entry.depth() > 0
+        && entry
+            .path()
+            .parent()
+            .and_then(Path::file_name)
+            .is_some_and(|name| prune.iter().any(|glob| glob.is_match(name)))
+}
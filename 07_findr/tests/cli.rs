@@ -3,7 +3,11 @@ use assert_cmd::Command;
 use predicates::prelude::*;
 use pretty_assertions::assert_eq;
 use rand::{distributions::Alphanumeric, Rng};
+#[cfg(not(windows))]
+use std::time::{Duration, SystemTime};
 use std::{borrow::Cow, fs, path::Path};
+#[cfg(not(windows))]
+use tempfile::TempDir;
 
 const PRG: &str = "findr";
 
@@ -39,13 +43,24 @@ fn skips_bad_dir() -> Result<()> {
 #[test]
 fn dies_bad_name() -> Result<()> {
     Command::cargo_bin(PRG)?
-        .args(["--name", "*.csv"])
+        .args(["--name-regex", "*.csv"])
         .assert()
         .failure()
         .stderr(predicate::str::contains("error: invalid value '*.csv'"));
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn dies_missing_name() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "--name"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("a value is required for '--name"));
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn dies_bad_type() -> Result<()> {
@@ -60,14 +75,14 @@ fn dies_bad_type() -> Result<()> {
 
 // --------------------------------------------------
 #[cfg(windows)]
-fn format_file_name(expected_file: &str) -> Cow<str> {
+fn format_file_name(expected_file: &str) -> Cow<'_, str> {
     // Equivalent to: Cow::Owned(format!("{}.windows", expected_file))
     format!("{}.windows", expected_file).into()
 }
 
 // --------------------------------------------------
 #[cfg(not(windows))]
-fn format_file_name(expected_file: &str) -> Cow<str> {
+fn format_file_name(expected_file: &str) -> Cow<'_, str> {
     // Equivalent to: Cow::Borrowed(expected_file)
     expected_file.into()
 }
@@ -224,11 +239,20 @@ fn type_f_l() -> Result<()> {
     )
 }
 
+// --------------------------------------------------
+#[test]
+fn type_f_l_commas() -> Result<()> {
+    run(
+        &["tests/inputs", "-t", "l,f"],
+        "tests/expected/type_f_l.txt",
+    )
+}
+
 // --------------------------------------------------
 #[test]
 fn name_csv() -> Result<()> {
     run(
-        &["tests/inputs", "-n", ".*[.]csv"],
+        &["tests/inputs", "--name-regex", ".*[.]csv"],
         "tests/expected/name_csv.txt",
     )
 }
@@ -237,7 +261,13 @@ fn name_csv() -> Result<()> {
 #[test]
 fn name_csv_mp3() -> Result<()> {
     run(
-        &["tests/inputs", "-n", ".*[.]csv", "-n", ".*[.]mp3"],
+        &[
+            "tests/inputs",
+            "--name-regex",
+            ".*[.]csv",
+            "--name-regex",
+            ".*[.]mp3",
+        ],
         "tests/expected/name_csv_mp3.txt",
     )
 }
@@ -246,7 +276,7 @@ fn name_csv_mp3() -> Result<()> {
 #[test]
 fn name_txt_path_a_d() -> Result<()> {
     run(
-        &["tests/inputs/a", "tests/inputs/d", "--name", ".*.txt"],
+        &["tests/inputs/a", "tests/inputs/d", "--name-regex", ".*.txt"],
         "tests/expected/name_txt_path_a_d.txt",
     )
 }
@@ -254,14 +284,17 @@ fn name_txt_path_a_d() -> Result<()> {
 // --------------------------------------------------
 #[test]
 fn name_a() -> Result<()> {
-    run(&["tests/inputs", "-n", "a"], "tests/expected/name_a.txt")
+    run(
+        &["tests/inputs", "--name-regex", "a"],
+        "tests/expected/name_a.txt",
+    )
 }
 
 // --------------------------------------------------
 #[test]
 fn type_f_name_a() -> Result<()> {
     run(
-        &["tests/inputs", "-t", "f", "-n", "a"],
+        &["tests/inputs", "-t", "f", "--name-regex", "a"],
         "tests/expected/type_f_name_a.txt",
     )
 }
@@ -270,11 +303,334 @@ fn type_f_name_a() -> Result<()> {
 #[test]
 fn type_d_name_a() -> Result<()> {
     run(
-        &["tests/inputs", "--type", "d", "--name", "a"],
+        &["tests/inputs", "--type", "d", "--name-regex", "a"],
         "tests/expected/type_d_name_a.txt",
     )
 }
 
+// --------------------------------------------------
+#[test]
+fn name_glob() -> Result<()> {
+    run(
+        &["tests/inputs", "--name", "*.csv"],
+        "tests/expected/name_csv.txt",
+    )?;
+    run(
+        &["tests/inputs", "-iname", "*.CSV"],
+        "tests/expected/name_csv.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn name_glob_is_anchored() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "--name", "cs"])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn path_glob() -> Result<()> {
+    run(
+        &["tests/inputs", "--path", "*/b/*"],
+        "tests/expected/path_glob_b.txt",
+    )?;
+    run(
+        &["tests/inputs", "-ipath", "TESTS/*/B/*"],
+        "tests/expected/path_glob_b.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn regex() -> Result<()> {
+    run(
+        &["tests/inputs", "--regex", ".*/[a-c][.][a-z]+"],
+        "tests/expected/regex_a_c.txt",
+    )?;
+    run(
+        &["tests/inputs", "-iregex", ".*/[A-C][.][A-Z]+"],
+        "tests/expected/regex_a_c.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn expr_name_or() -> Result<()> {
+    run(
+        &["tests/inputs", "-n", "*.csv", "-o", "-n", "*.mp3"],
+        "tests/expected/name_csv_mp3.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn expr_group_not() -> Result<()> {
+    run(
+        &[
+            "tests/inputs",
+            "(",
+            "-n",
+            "*.csv",
+            "--or",
+            "-n",
+            "*.txt",
+            ")",
+            "!",
+            "-n",
+            "[ab]*",
+        ],
+        "tests/expected/expr_group_not.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn expr_type_d_not_name() -> Result<()> {
+    run(
+        &["tests/inputs", "-t", "d", "-a", "-not", "-n", "inputs"],
+        "tests/expected/expr_type_d_not_name.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn expr_print_before_name() -> Result<()> {
+    run(
+        &["tests/inputs/a", "--print", "-n", "b"],
+        "tests/expected/path_a.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn expr_exec_before_name() -> Result<()> {
+    run(
+        &[
+            "tests/inputs/a",
+            "--exec",
+            "echo",
+            "X",
+            "{}",
+            ";",
+            "-n",
+            "b",
+        ],
+        "tests/expected/exec_echo_path_a.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_unbalanced_parens() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "(", "-n", "a"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("missing ')' to close '('"));
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "-n", "a", ")"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unexpected ')'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn max_depth_1() -> Result<()> {
+    run(
+        &["tests/inputs", "--max-depth", "1"],
+        "tests/expected/max_depth_1.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn min_depth_3() -> Result<()> {
+    run(
+        &["tests/inputs", "--min-depth", "3"],
+        "tests/expected/min_depth_3.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn prune_a_d() -> Result<()> {
+    run(
+        &["tests/inputs", "--prune", "[ad]"],
+        "tests/expected/prune_a_d.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn prune_b_depth_2() -> Result<()> {
+    run(
+        &[
+            "tests/inputs",
+            "--prune",
+            "b",
+            "--min-depth",
+            "2",
+            "--max-depth",
+            "2",
+        ],
+        "tests/expected/prune_b_depth_2.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn prune_search_path() -> Result<()> {
+    for jobs in ["1", "4"] {
+        Command::cargo_bin(PRG)?
+            .args(["tests/inputs/a", "--prune", "a", "-j", jobs])
+            .assert()
+            .success()
+            .stdout("tests/inputs/a\n");
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn exec_per_entry() -> Result<()> {
+    run(
+        &["tests/inputs/a", "--exec", "echo", "X", "{}", ";"],
+        "tests/expected/exec_echo_path_a.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn exec_batch() -> Result<()> {
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "-t", "d", "--exec", "echo", "{}", "+"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].split(' ').count(), 7);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn exec_predicate() -> Result<()> {
+    run(
+        &["tests/inputs", "-exec", "test", "-d", "{}", ";", "-print"],
+        "tests/expected/type_d.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn execdir() -> Result<()> {
+    run(
+        &["tests/inputs/a", "-t", "f", "--execdir", "echo", "{}", ";"],
+        "tests/expected/execdir_echo_path_a.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn dies_failed_batch() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "--exec", "false", "{}", "+"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("one or more commands failed"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_unterminated_exec() -> Result<()> {
+    for action in ["--exec", "-execdir", "-ok"] {
+        let expected = format!(
+            "missing argument to `--{}`",
+            action.trim_start_matches('-')
+        );
+        Command::cargo_bin(PRG)?
+            .args(["tests/inputs/a", action, "echo", "{}", "-name", "b.csv"])
+            .assert()
+            .failure()
+            .stdout("")
+            .stderr(predicate::str::contains(expected));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn print0() -> Result<()> {
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["tests/inputs/a/b/c", "--print0"])
+        .assert()
+        .success();
+    assert_eq!(
+        cmd.get_output().stdout,
+        b"tests/inputs/a/b/c\0tests/inputs/a/b/c/c.mp3\0"
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn printf() -> Result<()> {
+    run(
+        &["tests/inputs/a", "--printf", "%y %d %f %h\\n"],
+        "tests/expected/printf_path_a.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_printf() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--printf", "%z"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(r#"unknown directive "%z""#));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn fprint() -> Result<()> {
+    let outfile =
+        std::env::temp_dir().join(format!("findr-{}", gen_bad_file()));
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/a/b/c", "-t", "f", "--fprint"])
+        .arg(&outfile)
+        .assert()
+        .success()
+        .stdout("");
+    let contents = fs::read_to_string(&outfile)?;
+    fs::remove_file(&outfile)?;
+    assert_eq!(
+        contents,
+        format!(
+            "{}\n",
+            Path::new("tests/inputs/a/b/c").join("c.mp3").display()
+        )
+    );
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn path_g() -> Result<()> {
@@ -312,3 +668,585 @@ fn unreadable_dir() -> Result<()> {
     assert!(stderr.contains("cant-touch-this: Permission denied"));
     Ok(())
 }
+
+// --------------------------------------------------
+/// A directory of files made for one test, removed when it is dropped even
+/// if the test fails part way
+#[cfg(not(windows))]
+struct TestDir(TempDir);
+
+#[cfg(not(windows))]
+impl TestDir {
+    fn new() -> Result<Self> {
+        Ok(TestDir(
+            tempfile::Builder::new().prefix("findr-").tempdir()?,
+        ))
+    }
+
+    fn path(&self) -> &Path {
+        self.0.path()
+    }
+
+    /// Add directories along with any missing parents
+    fn dirs(self, dirs: &[&str]) -> Result<Self> {
+        for dir in dirs {
+            fs::create_dir_all(self.path().join(dir))?;
+        }
+        Ok(self)
+    }
+
+    /// Add a file in a directory that is already there
+    fn file(self, file: &str, contents: impl AsRef<[u8]>) -> Result<Self> {
+        fs::write(self.path().join(file), contents)?;
+        Ok(self)
+    }
+
+    /// Add empty files
+    fn files(self, files: &[&str]) -> Result<Self> {
+        files.iter().try_fold(self, |dir, file| dir.file(file, ""))
+    }
+
+    fn mode(self, file: &str, mode: u32) -> Result<Self> {
+        use std::os::unix::fs::PermissionsExt;
+        let permissions = fs::Permissions::from_mode(mode);
+        fs::set_permissions(self.path().join(file), permissions)?;
+        Ok(self)
+    }
+
+    fn modified(self, file: &str, time: SystemTime) -> Result<Self> {
+        fs::File::options()
+            .write(true)
+            .open(self.path().join(file))?
+            .set_modified(time)?;
+        Ok(self)
+    }
+
+    fn symlink(self, target: &str, link: &str) -> Result<Self> {
+        std::os::unix::fs::symlink(target, self.path().join(link))?;
+        Ok(self)
+    }
+
+    /// The lines of some output, sorted and with the directory cut from the
+    /// front of each path
+    fn relative(&self, output: &[u8]) -> Result<Vec<String>> {
+        let root = self.path().to_string_lossy();
+        let mut lines: Vec<String> = std::str::from_utf8(output)?
+            .lines()
+            .map(|line| line.replacen(root.as_ref(), "", 1))
+            .filter(|line| !line.is_empty())
+            .collect();
+        lines.sort();
+        Ok(lines)
+    }
+
+    /// Everything still in the directory, as `find` lists it
+    fn remaining(&self) -> Result<Vec<String>> {
+        let output = std::process::Command::new("find")
+            .arg(self.path())
+            .output()?;
+        self.relative(&output.stdout)
+    }
+}
+
+// --------------------------------------------------
+/// Files with known sizes, times and permissions: `old.txt` (3 bytes, 2020),
+/// `new.txt` (empty), `big.bin` (3000 bytes), `run.sh` (mode 755) and an
+/// empty directory `empty`
+#[cfg(not(windows))]
+fn scratch_dir() -> Result<TestDir> {
+    // 2020-01-01T00:00:00Z
+    let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_577_836_800);
+    TestDir::new()?
+        .dirs(&["empty"])?
+        .file("old.txt", "old")?
+        .file("new.txt", "")?
+        .file("big.bin", vec![0; 3000])?
+        .file("run.sh", "#!/bin/sh\n")?
+        .mode("old.txt", 0o644)?
+        .mode("new.txt", 0o644)?
+        .mode("big.bin", 0o644)?
+        .mode("run.sh", 0o755)?
+        .modified("old.txt", old)
+}
+
+// --------------------------------------------------
+#[cfg(not(windows))]
+fn run_scratch(args: &[&str], expected: &[&str]) -> Result<()> {
+    let dir = scratch_dir()?;
+    let cmd = Command::cargo_bin(PRG)?
+        .arg(dir.path())
+        .args(args)
+        .assert()
+        .success();
+    assert_eq!(dir.relative(&cmd.get_output().stdout)?, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn size() -> Result<()> {
+    run_scratch(&["-t", "f", "--size", "+2k"], &["/big.bin"])?;
+    run_scratch(&["-t", "f", "--size", "3c"], &["/old.txt"])?;
+    run_scratch(&["-t", "f", "--size", "-1"], &["/new.txt"])
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn mtime_newer() -> Result<()> {
+    run_scratch(&["-t", "f", "--mtime", "+30"], &["/old.txt"])?;
+    run_scratch(
+        &["-t", "f", "--mmin", "-60"],
+        &["/big.bin", "/new.txt", "/run.sh"],
+    )?;
+    run_scratch(
+        &[
+            "-t",
+            "f",
+            "--newer",
+            "tests/inputs/g.csv",
+            "!",
+            "--mtime",
+            "+30",
+        ],
+        &["/big.bin", "/new.txt", "/run.sh"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn perm() -> Result<()> {
+    run_scratch(&["-t", "f", "--perm", "755"], &["/run.sh"])?;
+    run_scratch(&["-t", "f", "--perm", "/111"], &["/run.sh"])?;
+    run_scratch(
+        &["-t", "f", "--perm", "-644"],
+        &["/big.bin", "/new.txt", "/old.txt", "/run.sh"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn empty() -> Result<()> {
+    run_scratch(&["--empty"], &["/empty", "/new.txt"])
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_size() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--size", "1x"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(r#"invalid size "1x""#));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[cfg(not(windows))]
+fn ignore_dir() -> Result<TestDir> {
+    TestDir::new()?
+        .dirs(&[
+            "repo/.git/info",
+            "repo/target",
+            "repo/src",
+            "home/.config/git",
+        ])?
+        .file("repo/.git/info/exclude", "*.log\n")?
+        .file("repo/.gitignore", "target/\n*.tmp\n!keep.tmp\n")?
+        .file("repo/.ignore", "!debug.log\n")?
+        .file("home/.config/git/ignore", "*.bak\n")?
+        .files(&[
+            "repo/target/out.o",
+            "repo/src/main.rs",
+            "repo/.env",
+            "repo/a.tmp",
+            "repo/keep.tmp",
+            "repo/app.log",
+            "repo/debug.log",
+            "repo/backup.bak",
+        ])
+}
+
+// --------------------------------------------------
+#[cfg(not(windows))]
+fn run_ignore(args: &[&str], expected: &[&str]) -> Result<()> {
+    let dir = ignore_dir()?;
+    let cmd = Command::cargo_bin(PRG)?
+        .env("HOME", dir.path().join("home"))
+        .env_remove("XDG_CONFIG_HOME")
+        .arg(dir.path().join("repo"))
+        .args(args)
+        .assert()
+        .success();
+    let lines = dir.relative(&cmd.get_output().stdout)?;
+    let expected: Vec<String> =
+        expected.iter().map(|path| format!("/repo{path}")).collect();
+    assert_eq!(lines, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn respect_ignore() -> Result<()> {
+    run_ignore(
+        &["--respect-ignore", "-t", "f"],
+        &["/debug.log", "/keep.tmp", "/src/main.rs"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn respect_ignore_hidden() -> Result<()> {
+    run_ignore(
+        &["--respect-ignore", "--hidden", "-t", "f"],
+        &[
+            "/.env",
+            "/.git/info/exclude",
+            "/.gitignore",
+            "/.ignore",
+            "/debug.log",
+            "/keep.tmp",
+            "/src/main.rs",
+        ],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn ignore_files_unused_by_default() -> Result<()> {
+    run_ignore(
+        &["-t", "f", "--name", "*.tmp", "--name", "*.o"],
+        &["/a.tmp", "/keep.tmp", "/target/out.o"],
+    )
+}
+
+// --------------------------------------------------
+fn run_sorted(args: &[&str], expected_file: &str) -> Result<()> {
+    let file = format_file_name(expected_file);
+    let expected = fs::read_to_string(file.as_ref())?;
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn jobs_4() -> Result<()> {
+    run(&["tests/inputs", "-j", "4"], "tests/expected/path1.txt")
+}
+
+// --------------------------------------------------
+#[test]
+fn jobs_type_f_name_a() -> Result<()> {
+    run(
+        &[
+            "tests/inputs",
+            "--jobs",
+            "0",
+            "-t",
+            "f",
+            "--name-regex",
+            "a",
+        ],
+        "tests/expected/type_f_name_a.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn sort() -> Result<()> {
+    run_sorted(&["tests/inputs", "--sort"], "tests/expected/sort_path1.txt")
+}
+
+// --------------------------------------------------
+#[test]
+fn jobs_sort() -> Result<()> {
+    run_sorted(
+        &["tests/inputs", "-j", "4", "--sort"],
+        "tests/expected/sort_path1.txt",
+    )
+}
+
+// --------------------------------------------------
+#[cfg(not(windows))]
+fn link_dir() -> Result<TestDir> {
+    TestDir::new()?
+        .dirs(&["dir"])?
+        .files(&["dir/file"])?
+        .symlink("..", "dir/loop")?
+        .symlink("dir", "link")?
+        .symlink("nowhere", "dangling")
+}
+
+// --------------------------------------------------
+#[cfg(not(windows))]
+fn run_links(
+    path: &str,
+    args: &[&str],
+    expected: &[&str],
+    stderr: &str,
+) -> Result<()> {
+    let dir = link_dir()?;
+    let cmd = Command::cargo_bin(PRG)?
+        .arg(dir.path().join(path))
+        .args(args)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(stderr));
+    assert_eq!(dir.relative(&cmd.get_output().stdout)?, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn links_not_followed() -> Result<()> {
+    let expected =
+        ["/", "/dangling", "/dir", "/dir/file", "/dir/loop", "/link"];
+    run_links("", &[], &expected, "")?;
+    run_links("", &["-L", "-H", "-P"], &expected, "")?;
+    run_links("link", &[], &["/link"], "")
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn links_followed() -> Result<()> {
+    let expected = ["/", "/dir", "/dir/file", "/link", "/link/file"];
+    for args in [&["-L"][..], &["-L", "-j", "4"]] {
+        run_links("", args, &expected, "File system loop found")?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn links_followed_from_command_line() -> Result<()> {
+    let expected = ["/link", "/link/file", "/link/loop"];
+    run_links("link", &["-H"], &expected, "")?;
+    run_links("link", &["-H", "-j", "4"], &expected, "")?;
+    run_links("link", &["-H", "-t", "d"], &["/link"], "")
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn xtype() -> Result<()> {
+    run_links(
+        "",
+        &["--xtype", "d"],
+        &["/", "/dir", "/dir/loop", "/link"],
+        "",
+    )?;
+    run_links("", &["-xtype", "l"], &["/dangling"], "")?;
+    run_links("", &["-L", "--xtype", "l"], &["/link"], "")
+}
+
+// --------------------------------------------------
+#[test]
+fn depth_sort() -> Result<()> {
+    run_sorted(
+        &["tests/inputs", "--depth", "--sort"],
+        "tests/expected/depth_sort_path1.txt",
+    )?;
+    run_sorted(
+        &["tests/inputs", "-depth", "--sort", "-j", "4"],
+        "tests/expected/depth_sort_path1.txt",
+    )
+}
+
+// --------------------------------------------------
+#[cfg(not(windows))]
+fn delete_dir() -> Result<TestDir> {
+    TestDir::new()?
+        .dirs(&["tree/a/b", "tree/keep", "outside"])?
+        .files(&[
+            "tree/a/b/x.tmp",
+            "tree/a/y.tmp",
+            "tree/keep/z.txt",
+            "outside/o.tmp",
+        ])?
+        .symlink("../outside", "tree/link")
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn delete() -> Result<()> {
+    let dir = delete_dir()?;
+    Command::cargo_bin(PRG)?
+        .arg(dir.path().join("tree"))
+        .args([
+            "(", "-name", "*.tmp", "-o", "-name", "a", "-o", "-name", "b",
+        ])
+        .args([")", "-delete"])
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(
+        dir.remaining()?,
+        [
+            "/outside",
+            "/outside/o.tmp",
+            "/tree",
+            "/tree/keep",
+            "/tree/keep/z.txt",
+            "/tree/link"
+        ]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn delete_dry_run() -> Result<()> {
+    let dir = delete_dir()?;
+    let cmd = Command::cargo_bin(PRG)?
+        .arg(dir.path().join("tree"))
+        .args(["--name", "*.tmp", "--delete", "--dry-run"])
+        .assert()
+        .success();
+    assert_eq!(
+        dir.relative(&cmd.get_output().stdout)?,
+        ["/tree/a/b/x.tmp", "/tree/a/y.tmp"]
+    );
+    assert_eq!(dir.remaining()?.len(), 10);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn delete_keeps_search_path() -> Result<()> {
+    let dir = delete_dir()?;
+    Command::cargo_bin(PRG)?
+        .arg(dir.path().join("tree"))
+        .args(["--max-depth", "0", "--delete"])
+        .assert()
+        .success()
+        .stderr("");
+    assert!(dir.path().join("tree").is_dir());
+
+    Command::cargo_bin(PRG)?
+        .arg(dir.path().join("tree"))
+        .arg("--delete")
+        .assert()
+        .success()
+        .stderr("");
+    assert_eq!(dir.remaining()?, ["/outside", "/outside/o.tmp", "/tree"]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn dies_delete_outside_search_path() -> Result<()> {
+    let dir = delete_dir()?;
+    Command::cargo_bin(PRG)?
+        .arg(dir.path().join("tree"))
+        .args(["-L", "--name", "o.tmp", "--delete"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("refusing to delete outside of"));
+    assert!(dir.path().join("outside/o.tmp").is_file());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn special_types() -> Result<()> {
+    let dir = TestDir::new()?.files(&["file"])?;
+    std::process::Command::new("mkfifo")
+        .arg(dir.path().join("fifo"))
+        .status()?;
+    let _socket =
+        std::os::unix::net::UnixListener::bind(dir.path().join("socket"))?;
+
+    let find = |args: &[&str]| -> Result<String> {
+        let output = Command::cargo_bin(PRG)?
+            .arg(dir.path())
+            .args(args)
+            .args(["--printf", "%f %y\\n"])
+            .output()?;
+        Ok(dir.relative(&output.stdout)?.join(","))
+    };
+    assert_eq!(find(&["-t", "p"])?, "fifo p");
+    assert_eq!(find(&["-t", "s"])?, "socket s");
+    assert_eq!(find(&["-type", "p,s"])?, "fifo p,socket s");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn device_types() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["/dev/null", "-t", "c", "-o", "-t", "b"])
+        .assert()
+        .success()
+        .stdout("/dev/null\n");
+    Command::cargo_bin(PRG)?
+        .args(["/dev/null", "-t", "f,d,l,b,p,s"])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn closed_stdout() -> Result<()> {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    // Far more output than a pipe holds, so findr is still writing when
+    // the reader goes away
+    let names: Vec<String> = (0..2000).map(|i| format!("{i:0>60}")).collect();
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    let dir = TestDir::new()?.files(&names)?;
+    for jobs in ["1", "4"] {
+        let mut child =
+            std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+                .arg(dir.path())
+                .args(["-j", jobs])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()?;
+        let mut first = String::new();
+        BufReader::new(child.stdout.take().unwrap()).read_line(&mut first)?;
+        let output = child.wait_with_output()?;
+        assert_eq!(first.trim_end(), dir.path().to_string_lossy());
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8(output.stderr)?, "");
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn failed_write() -> Result<()> {
+    let output = std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+        .arg("tests/inputs")
+        .stdout(fs::File::create("/dev/full")?)
+        .output()?;
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stderr)?,
+        "No space left on device (os error 28)\n"
+    );
+    Ok(())
+}
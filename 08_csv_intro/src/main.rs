@@ -1,20 +1,428 @@
-use std::fs::File;
+use std::{
+    env,
+    fs::File,
+    io::{self, BufRead, BufReader, ErrorKind, IsTerminal, Write},
+    num::NonZeroUsize,
+    process::{Child, Command, Stdio},
+};
 
+use anyhow::{bail, Result};
+use clap::{Parser, ValueEnum};
 use csv::{ReaderBuilder, StringRecord};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-fn main() -> std::io::Result<()> {
-    let mut reader = ReaderBuilder::new()
-        .delimiter(b',')
-        .from_reader(File::open("books.csv")?);
+/// Used when $PAGER is not set: quit if the table fits on one screen and
+/// scroll long rows sideways instead of wrapping them
+const DEFAULT_PAGER: &str = "less -FRSX";
 
-    println!("{}", fmt(reader.headers()?));
-    for record in reader.records() {
-        println!("{}", fmt(&record?))
+#[derive(Debug, Parser)]
+#[command(author, version, about)]
+/// Render CSV as a table in the terminal
+struct Args {
+    /// Input file(s)
+    #[arg(value_name = "FILES", default_value = "-")]
+    files: Vec<String>,
+
+    /// Field delimiter
+    #[arg(value_name = "DELIMITER", short, long, default_value = ",")]
+    delimiter: String,
+
+    /// Treat the first row as data instead of a header
+    #[arg(short = 'H', long)]
+    no_header: bool,
+
+    /// Widest a column gets, longer cells are cut with …
+    #[arg(value_name = "WIDTH", short = 'w', long, default_value = "40")]
+    max_width: NonZeroUsize,
+
+    /// Rows read to size the columns, 0 for all of them. Later rows are cut
+    /// to fit.
+    #[arg(value_name = "ROWS", long, default_value_t = 1000)]
+    sample: usize,
+
+    /// Characters to draw the borders with
+    #[arg(value_name = "STYLE", long, value_enum, default_value_t = Border::Unicode)]
+    border: Border,
+
+    /// Write to stdout instead of through $PAGER
+    #[arg(long)]
+    no_pager: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Border {
+    Unicode,
+    Ascii,
+}
+
+/// The characters of a border style. Each rule is its left end, the
+/// crossing between columns and its right end.
+struct BoxChars {
+    horizontal: char,
+    vertical: char,
+    top: [char; 3],
+    middle: [char; 3],
+    bottom: [char; 3],
+}
+
+impl Border {
+    fn chars(self) -> BoxChars {
+        match self {
+            Border::Unicode => BoxChars {
+                horizontal: '─',
+                vertical: '│',
+                top: ['┌', '┬', '┐'],
+                middle: ['├', '┼', '┤'],
+                bottom: ['└', '┴', '┘'],
+            },
+            Border::Ascii => BoxChars {
+                horizontal: '-',
+                vertical: '|',
+                top: ['+'; 3],
+                middle: ['+'; 3],
+                bottom: ['+'; 3],
+            },
+        }
+    }
+}
+
+/// How a column is drawn, worked out from the sampled rows
+#[derive(Debug, PartialEq, Eq)]
+struct Column {
+    width: usize,
+    numeric: bool,
+}
+
+struct Table {
+    columns: Vec<Column>,
+    chars: BoxChars,
+}
+
+impl Table {
+    /// A horizontal line across the table, `ends` from `self.chars`
+    fn rule(&self, [left, cross, right]: [char; 3]) -> String {
+        let lines: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| self.chars.horizontal.to_string().repeat(column.width + 2))
+            .collect();
+        format!("{left}{}{right}", lines.join(&cross.to_string()))
+    }
+
+    /// One record between vertical borders, numbers right-aligned below the
+    /// header. Cells past the sampled columns have nowhere to go, so the last
+    /// column ends with … to show that some were left out.
+    fn row(&self, record: &StringRecord, is_header: bool) -> String {
+        let last = self.columns.len().saturating_sub(1);
+        let cut = record.len() > self.columns.len();
+        let cells: Vec<String> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                let cell = clean(record.get(i).unwrap_or(""));
+                let fitted = if cut && i == last {
+                    fit_marked(&cell, column.width)
+                } else {
+                    fit(&cell, column.width)
+                };
+                pad(&fitted, column.width, column.numeric && !is_header)
+            })
+            .collect();
+        let vertical = self.chars.vertical;
+        format!(
+            "{vertical} {} {vertical}",
+            cells.join(&format!(" {vertical} "))
+        )
+    }
+}
+
+fn main() {
+    if let Err(e) = run(Args::parse()) {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
+
+fn run(args: Args) -> Result<()> {
+    let delimiter = match args.delimiter.as_bytes() {
+        [byte] => *byte,
+        _ => bail!(r#"--delim "{}" must be a single byte"#, args.delimiter),
+    };
+
+    let mut pager = if args.no_pager || !io::stdout().is_terminal() {
+        None
+    } else {
+        spawn_pager()
+    };
+    let mut out: Box<dyn Write> = match pager.as_mut().and_then(|pager| pager.stdin.take()) {
+        Some(stdin) => Box::new(stdin),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    for (file_num, filename) in args.files.iter().enumerate() {
+        match open(filename) {
+            Err(err) => eprintln!("{filename}: {err}"),
+            Ok(file) => {
+                if file_num > 0 {
+                    writeln!(out)?;
+                }
+                let result = look(&args, delimiter, file, &mut out);
+                // Quitting the pager early is not an error
+                if let Err(e) = result {
+                    match e.downcast_ref::<io::Error>() {
+                        Some(e) if e.kind() == ErrorKind::BrokenPipe => break,
+                        _ => eprintln!("{filename}: {e}"),
+                    }
+                }
+            }
+        }
+    }
+
+    drop(out);
+    if let Some(mut pager) = pager {
+        pager.wait()?;
+    }
+    Ok(())
+}
+
+/// Start $PAGER with its stdin to write to, or nothing if it cannot run
+fn spawn_pager() -> Option<Child> {
+    let pager = env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_PAGER.to_string());
+    let mut words = pager.split_whitespace();
+    Command::new(words.next()?)
+        .args(words)
+        .stdin(Stdio::piped())
+        .spawn()
+        .ok()
+}
+
+fn open(filename: &str) -> Result<Box<dyn BufRead>> {
+    match filename {
+        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
+        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
     }
+}
 
+/// Draw one file as a table. The columns are sized from the header and the
+/// first `--sample` rows, the rest are streamed through.
+fn look(args: &Args, delimiter: u8, file: impl BufRead, out: &mut impl Write) -> Result<()> {
+    let mut records = ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(file)
+        .into_records();
+
+    let header = if args.no_header {
+        None
+    } else {
+        records.next().transpose()?
+    };
+    let mut sample = vec![];
+    for record in records.by_ref() {
+        sample.push(record?);
+        if sample.len() == args.sample {
+            break;
+        }
+    }
+    if header.is_none() && sample.is_empty() {
+        return Ok(());
+    }
+
+    let table = Table {
+        columns: layout(header.as_ref(), &sample, args.max_width.get()),
+        chars: args.border.chars(),
+    };
+    writeln!(out, "{}", table.rule(table.chars.top))?;
+    if let Some(header) = &header {
+        writeln!(out, "{}", table.row(header, true))?;
+        writeln!(out, "{}", table.rule(table.chars.middle))?;
+    }
+    for record in sample {
+        writeln!(out, "{}", table.row(&record, false))?;
+    }
+    for record in records {
+        writeln!(out, "{}", table.row(&record?, false))?;
+    }
+    writeln!(out, "{}", table.rule(table.chars.bottom))?;
     Ok(())
 }
 
-fn fmt(rec: &StringRecord) -> String {
-    rec.into_iter().map(|v| format!("{:20}", v)).collect()
+/// Size each column to its widest cell up to `max_width`. A column is
+/// numeric when every cell below the header that is not empty is a number.
+fn layout(header: Option<&StringRecord>, rows: &[StringRecord], max_width: usize) -> Vec<Column> {
+    let num_columns = header
+        .into_iter()
+        .chain(rows)
+        .map(StringRecord::len)
+        .max()
+        .unwrap_or(0);
+    (0..num_columns)
+        .map(|i| {
+            let cells = || rows.iter().filter_map(|record| record.get(i));
+            let width = header
+                .and_then(|header| header.get(i))
+                .into_iter()
+                .chain(cells())
+                .map(|cell| clean(cell).width())
+                .max()
+                .unwrap_or(0);
+            let mut values = cells().filter(|cell| !cell.is_empty()).peekable();
+            let numeric =
+                values.peek().is_some() && values.all(|cell| cell.trim().parse::<f64>().is_ok());
+            // Even an empty column needs room for the … of a cut cell
+            Column {
+                width: width.min(max_width).max(1),
+                numeric,
+            }
+        })
+        .collect()
+}
+
+/// Keep a cell on one line of the table
+fn clean(cell: &str) -> String {
+    cell.chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+/// Cut a cell that is wider than `width` columns, ending it with …
+fn fit(cell: &str, width: usize) -> String {
+    if cell.width() <= width {
+        return cell.to_string();
+    }
+    let mut fitted = String::new();
+    let mut used = 0;
+    for c in cell.chars() {
+        let c_width = c.width().unwrap_or(0);
+        if used + c_width + 1 > width {
+            break;
+        }
+        used += c_width;
+        fitted.push(c);
+    }
+    fitted.push('…');
+    fitted
+}
+
+/// Like `fit`, but always ending with … even when the cell is not cut
+fn fit_marked(cell: &str, width: usize) -> String {
+    fit(&format!("{cell}…"), width)
+}
+
+/// Fill a cell out to `width` columns, on the left to align it right
+fn pad(cell: &str, width: usize, align_right: bool) -> String {
+    let padding = " ".repeat(width.saturating_sub(cell.width()));
+    if align_right {
+        format!("{padding}{cell}")
+    } else {
+        format!("{cell}{padding}")
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use csv::StringRecord;
+
+    use super::{fit, fit_marked, layout, pad, Border, Column, Table};
+
+    #[test]
+    fn test_fit() {
+        assert_eq!(fit("", 3), "");
+        assert_eq!(fit("abc", 3), "abc");
+        assert_eq!(fit("abcd", 3), "ab…");
+        assert_eq!(fit("abcd", 1), "…");
+        assert_eq!(fit("Émile", 4), "Émi…");
+        // Wide characters take two columns
+        assert_eq!(fit("日本語", 4), "日…");
+
+        assert_eq!(fit_marked("ab", 3), "ab…");
+        assert_eq!(fit_marked("abcd", 3), "ab…");
+        assert_eq!(fit_marked("", 1), "…");
+    }
+
+    #[test]
+    fn test_pad() {
+        assert_eq!(pad("ab", 4, false), "ab  ");
+        assert_eq!(pad("12", 4, true), "  12");
+        assert_eq!(pad("日", 4, false), "日  ");
+        assert_eq!(pad("abcd", 2, true), "abcd");
+    }
+
+    #[test]
+    fn test_layout() {
+        let header = StringRecord::from(vec!["Author", "Year", "Title"]);
+        let rows = vec![
+            StringRecord::from(vec!["Émile Zola", "1865", "La Confession de Claude"]),
+            StringRecord::from(vec!["Samuel Beckett", "", "Waiting for Godot", "extra"]),
+        ];
+        assert_eq!(
+            layout(Some(&header), &rows, 20),
+            vec![
+                Column {
+                    width: 14,
+                    numeric: false
+                },
+                Column {
+                    width: 4,
+                    numeric: true
+                },
+                Column {
+                    width: 20,
+                    numeric: false
+                },
+                Column {
+                    width: 5,
+                    numeric: false
+                },
+            ]
+        );
+
+        // A header alone has no numeric columns
+        assert_eq!(
+            layout(Some(&header), &[], 20)[1],
+            Column {
+                width: 4,
+                numeric: false
+            }
+        );
+        assert!(layout(None, &[], 20).is_empty());
+    }
+
+    #[test]
+    fn test_table() {
+        let table = Table {
+            columns: vec![
+                Column {
+                    width: 3,
+                    numeric: false,
+                },
+                Column {
+                    width: 2,
+                    numeric: true,
+                },
+            ],
+            chars: Border::Ascii.chars(),
+        };
+        assert_eq!(table.rule(table.chars.top), "+-----+----+");
+        let record = StringRecord::from(vec!["ab", "1"]);
+        assert_eq!(table.row(&record, false), "| ab  |  1 |");
+        assert_eq!(table.row(&record, true), "| ab  | 1  |");
+        let record = StringRecord::from(vec!["a\tb"]);
+        assert_eq!(table.row(&record, false), "| a b |    |");
+        let record = StringRecord::from(vec!["a", "1", "too wide to fit"]);
+        assert_eq!(table.row(&record, false), "| a   | 1… |");
+        let record = StringRecord::from(vec!["a", "12", "x"]);
+        assert_eq!(table.row(&record, false), "| a   | 1… |");
+
+        let table = Table {
+            chars: Border::Unicode.chars(),
+            ..table
+        };
+        assert_eq!(table.rule(table.chars.bottom), "└─────┴────┘");
+    }
 }
@@ -0,0 +1,202 @@
+use anyhow::Result;
+use assert_cmd::Command;
+use predicates::prelude::*;
+use pretty_assertions::assert_eq;
+use rand::{distributions::Alphanumeric, Rng};
+use std::fs;
+
+const PRG: &str = "csvlook";
+const BOOKS: &str = "tests/inputs/books.csv";
+const PRICES: &str = "tests/inputs/prices.csv";
+const GROWING: &str = "tests/inputs/growing.csv";
+const RAGGED: &str = "tests/inputs/ragged.csv";
+
+// --------------------------------------------------
+fn random_string() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(7)
+        .map(char::from)
+        .collect()
+}
+
+// --------------------------------------------------
+fn gen_bad_file() -> String {
+    loop {
+        let filename = random_string();
+        if fs::metadata(&filename).is_err() {
+            return filename;
+        }
+    }
+}
+
+// --------------------------------------------------
+#[test]
+fn skips_bad_file() -> Result<()> {
+    let bad = gen_bad_file();
+    let expected = format!("{bad}: .* [(]os error 2[)]");
+    Command::cargo_bin(PRG)?
+        .args([BOOKS, &bad])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_match(expected)?);
+    Ok(())
+}
+
+// --------------------------------------------------
+fn dies(args: &[&str], expected: &str) -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(expected));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_delimiter() -> Result<()> {
+    dies(
+        &["-d", ",,", BOOKS],
+        r#"--delim ",," must be a single byte"#,
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_zero_width() -> Result<()> {
+    dies(&["-w", "0", BOOKS], "invalid value '0'")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_border() -> Result<()> {
+    dies(&["--border", "double", BOOKS], "invalid value 'double'")
+}
+
+// --------------------------------------------------
+fn run(args: &[&str], expected_file: &str) -> Result<()> {
+    let expected = fs::read_to_string(expected_file)?;
+    let output = Command::cargo_bin(PRG)?.args(args).output().expect("fail");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("invalid UTF-8");
+    assert_eq!(stdout, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn books() -> Result<()> {
+    run(&[BOOKS], "tests/expected/books.csv.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn books_stdin() -> Result<()> {
+    let input = fs::read_to_string(BOOKS)?;
+    let expected = fs::read_to_string("tests/expected/books.csv.out")?;
+    let output = Command::cargo_bin(PRG)?
+        .write_stdin(input)
+        .output()
+        .expect("fail");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn books_ascii() -> Result<()> {
+    run(
+        &["--border", "ascii", BOOKS],
+        "tests/expected/books.csv.ascii.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn books_max_width() -> Result<()> {
+    run(&["-w", "10", BOOKS], "tests/expected/books.csv.w10.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn books_no_header() -> Result<()> {
+    run(&["-H", BOOKS], "tests/expected/books.csv.H.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn books_tsv() -> Result<()> {
+    run(
+        &["-d", "\t", "tests/inputs/books.tsv"],
+        "tests/expected/books.tsv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn prices() -> Result<()> {
+    run(&[PRICES], "tests/expected/prices.csv.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn prices_max_width() -> Result<()> {
+    run(
+        &["--max-width", "20", PRICES],
+        "tests/expected/prices.csv.w20.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn growing() -> Result<()> {
+    run(&[GROWING], "tests/expected/growing.csv.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn growing_sample() -> Result<()> {
+    run(
+        &["--sample", "1", GROWING],
+        "tests/expected/growing.csv.sample1.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn ragged() -> Result<()> {
+    run(&[RAGGED], "tests/expected/ragged.csv.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn ragged_sample() -> Result<()> {
+    run(
+        &["--sample", "1", RAGGED],
+        "tests/expected/ragged.csv.sample1.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn blank_column_sample() -> Result<()> {
+    run(
+        &["--sample", "1", "tests/inputs/blank.csv"],
+        "tests/expected/blank.csv.sample1.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn empty() -> Result<()> {
+    run(&["tests/inputs/empty.csv"], "tests/expected/empty.csv.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn multiple_files() -> Result<()> {
+    run(&[BOOKS, GROWING], "tests/expected/all.out")
+}
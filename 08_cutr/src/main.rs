@@ -1,14 +1,16 @@
 use std::{
     fs::File,
-    io::{self, BufRead, BufReader},
+    io::{self, BufRead, BufReader, Write},
     num::NonZeroUsize,
     ops::Range,
 };
 
 use anyhow::{anyhow, bail, Result};
-use clap::{Arg, ArgGroup, Command, Parser};
-use csv::{ReaderBuilder, StringRecord, WriterBuilder};
+use clap::{value_parser, Arg, ArgAction, ArgGroup, Command, Parser, ValueEnum};
+use csv::{ByteRecord, ReaderBuilder, StringRecord, WriterBuilder};
 use regex::Regex;
+use serde_json::Value;
+use unicode_segmentation::UnicodeSegmentation;
 
 #[derive(Debug, Parser)]
 #[command(author, version, about)]
@@ -18,34 +20,171 @@ struct Args {
     #[arg(value_name = "FILES", default_value = "-")]
     files: Vec<String>,
 
-    /// Field delimiter
+    /// Field delimiter, any string
     #[arg(value_name = "DELIMITER", default_value = "\t", long, short)]
     delimiter: String,
 
+    /// Split fields on matches of a regular expression, like \s+
+    #[arg(
+        value_name = "REGEX",
+        long,
+        conflicts_with_all = ["delimiter", "whitespace", "bytes", "chars"]
+    )]
+    regex_delimiter: Option<String>,
+
+    /// Split fields on runs of spaces and tabs, ignoring those around the line
+    #[arg(short, long, conflicts_with_all = ["delimiter", "bytes", "chars"])]
+    whitespace: bool,
+
+    /// Delimiter written between the selected fields, the input delimiter
+    /// by default or a tab with --regex-delimiter and -w
+    #[arg(value_name = "STRING", long, conflicts_with_all = ["bytes", "chars"])]
+    output_delimiter: Option<String>,
+
+    /// Skip lines that contain no delimiter
+    #[arg(short = 's', long, conflicts_with_all = ["bytes", "chars"])]
+    only_delimited: bool,
+
+    /// Count -c positions in user-perceived characters, so that combining
+    /// marks and emoji sequences are never split
+    #[arg(long, conflicts_with_all = ["fields", "field_names", "bytes"])]
+    graphemes: bool,
+
+    /// Do not split multi-byte characters with -b: a range starts at the
+    /// beginning of its first character and ends before a character it cuts
+    #[arg(short = 'n', conflicts_with_all = ["fields", "field_names", "chars"])]
+    no_split: bool,
+
+    /// Select everything except the listed positions
+    #[arg(long)]
+    complement: bool,
+
+    /// Treat the first line of each file as a header, written only once
+    #[arg(long, conflicts_with_all = ["bytes", "chars"])]
+    header: bool,
+
+    /// Input format, ndjson selects -f keys or JSON pointers like /user/id
+    /// from one JSON object per line
+    #[arg(value_name = "FORMAT", long, value_enum, default_value_t = Format::Delimited)]
+    format: Format,
+
+    /// How --format ndjson writes the selected values [default: tsv]
+    #[arg(value_name = "FORMAT", long, value_enum)]
+    output_format: Option<OutputFormat>,
+
+    /// How fields are read [default: csv, or raw when the delimiter is not
+    /// a single byte]
+    #[arg(value_name = "MODE", long, value_enum)]
+    mode: Option<Mode>,
+
+    /// Quote character for --mode csv [default: "]
+    #[arg(value_name = "CHAR", long)]
+    quote: Option<String>,
+
+    /// Escape character for quotes inside quoted fields in --mode csv,
+    /// instead of doubling them
+    #[arg(value_name = "CHAR", long)]
+    escape: Option<String>,
+
+    /// Allow records of different lengths in --mode csv, or not with
+    /// --flexible=false [default: true]
+    #[arg(value_name = "BOOL", long, num_args(0..=1), require_equals = true, default_missing_value = "true")]
+    flexible: Option<bool>,
+
     #[command(flatten)]
     extract: ArgsExtract,
 }
 #[derive(Debug, clap::Args)]
 #[group(required = true, multiple = false)]
 struct ArgsExtract {
-    /// Selected fields
-    #[arg(value_name = "FIELDS", short, long)]
+    /// Selected fields, like 1,3-5 or 7-
+    #[arg(value_name = "FIELDS", short, long, allow_hyphen_values = true)]
     fields: Option<String>,
 
-    /// Selected bytes
-    #[arg(value_name = "BYTES", short, long)]
+    /// Selected fields by header name, like name,email,id, in that order.
+    /// Implies --header
+    #[arg(value_name = "NAMES", short = 'F', long)]
+    field_names: Option<String>,
+
+    /// Selected bytes, like 1,3-5 or 7-
+    #[arg(value_name = "BYTES", short, long, allow_hyphen_values = true)]
     bytes: Option<String>,
 
-    /// Selected chars
-    #[arg(value_name = "CHARS", short, long)]
+    /// Selected chars, like 1,3-5 or 7-
+    #[arg(value_name = "CHARS", short, long, allow_hyphen_values = true)]
     chars: Option<String>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Lines of fields split by a delimiter
+    Delimited,
+    /// Newline-delimited JSON
+    Ndjson,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Tsv,
+    Csv,
+    Ndjson,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Mode {
+    /// Split lines on every delimiter and keep the bytes as they are, like cut
+    Raw,
+    /// Parse quoted fields as in RFC 4180 and quote them again on output
+    Csv,
+}
+
+/// Where --mode raw splits a line into fields
+#[derive(Debug)]
+enum Splitter {
+    Byte(u8),
+    Pattern(regex::bytes::Regex),
+    Whitespace,
+}
+
+impl Splitter {
+    /// Split a line on every delimiter, quotes and all
+    fn split(&self, line: &[u8]) -> ByteRecord {
+        match self {
+            Splitter::Byte(delimiter) => line.split(|b| b == delimiter).collect(),
+            Splitter::Pattern(regex) => regex.split(line).collect(),
+            Splitter::Whitespace => {
+                let record: ByteRecord = line
+                    .split(|&b| b == b' ' || b == b'\t')
+                    .filter(|field| !field.is_empty())
+                    .collect();
+                // A line with no two fields is passed through, blanks and all
+                if record.len() > 1 {
+                    record
+                } else {
+                    ByteRecord::from(vec![line])
+                }
+            }
+        }
+    }
+}
+
+/// Zero-based ranges, an open end is `usize::MAX`
 type PositionList = Vec<Range<usize>>;
 
+/// A key or a JSON pointer selected from --format ndjson records
+#[derive(Debug, PartialEq, Eq)]
+struct KeyPath {
+    /// As given, the column header or key of the output
+    name: String,
+    pointer: String,
+}
+
 #[derive(Debug)]
 enum Extract {
     Fields(PositionList),
+    KeyPaths(Vec<KeyPath>),
+    /// Found in the header of each file, which may order them differently
+    FieldNames(Vec<String>),
     Bytes(PositionList),
     Chars(PositionList),
 }
@@ -58,51 +197,249 @@ fn main() {
 }
 
 fn run(args: Args) -> Result<()> {
-    let delim_bytes = args.delimiter.as_bytes();
-    if delim_bytes.len() != 1 {
-        bail!(r#"--delim "{}" must be a single byte"#, args.delimiter); // Raw string. Starts with r followed by 0 or more #, followed by ". " can be used inside it without escaping \"
+    let splitter = if args.whitespace {
+        Splitter::Whitespace
+    } else if let Some(regex) = &args.regex_delimiter {
+        let regex = regex::bytes::Regex::new(regex)
+            .map_err(|_| anyhow!(r#"Invalid --regex-delimiter "{regex}""#))?;
+        if regex.is_match(b"") {
+            bail!(r#"--regex-delimiter "{regex}" must not match an empty string"#);
+        }
+        Splitter::Pattern(regex)
+    } else {
+        match args.delimiter.as_bytes() {
+            [] => bail!(r#"--delim "" must not be empty"#),
+            [byte] => Splitter::Byte(*byte),
+            _ => Splitter::Pattern(regex::bytes::Regex::new(&regex::escape(&args.delimiter))?),
+        }
+    };
+    let mode = args.mode.unwrap_or(match splitter {
+        Splitter::Byte(_) => Mode::Csv,
+        _ => Mode::Raw,
+    });
+    // The csv reader only splits on a single byte
+    let csv_delimiter = match (mode, &splitter) {
+        (Mode::Csv, Splitter::Byte(delimiter)) => Some(*delimiter),
+        (Mode::Csv, _) if args.whitespace || args.regex_delimiter.is_some() => {
+            bail!("--regex-delimiter and -w only apply to --mode raw")
+        }
+        (Mode::Csv, _) => bail!(
+            r#"--delim "{}" must be a single byte for --mode csv"#,
+            args.delimiter
+        ),
+        (Mode::Raw, _) => None,
+    };
+    if mode == Mode::Raw && (args.quote.is_some() || args.escape.is_some() || args.flexible.is_some()) {
+        bail!("--quote, --escape and --flexible only apply to --mode csv");
     }
-    let delimiter: u8 = *delim_bytes.first().unwrap();
+    let quote = args
+        .quote
+        .as_deref()
+        .map_or(Ok(b'"'), |quote| single_byte("--quote", quote))?;
+    let escape = args
+        .escape
+        .as_deref()
+        .map(|escape| single_byte("--escape", escape))
+        .transpose()?;
+
+    // The csv writer only takes a single byte, raw fields and any other
+    // --output-delimiter are joined in by hand without quoting, like GNU cut
+    let output_delimiter = args.output_delimiter.unwrap_or(match splitter {
+        Splitter::Whitespace => "\t".to_string(),
+        _ if args.regex_delimiter.is_some() => "\t".to_string(),
+        _ => args.delimiter,
+    });
+    let (output_byte, joiner) = match (mode, output_delimiter.as_bytes()) {
+        (Mode::Csv, [byte]) => (*byte, None),
+        (_, joiner) => (b'\t', Some(joiner)),
+    };
 
-    let extract = if let Some(fields) = args.extract.fields.map(parse_pos).transpose()?
+    let select = |list: String| -> Result<PositionList> {
+        let positions = merge_pos(parse_pos(list)?);
+        Ok(if args.complement {
+            complement_pos(&positions)
+        } else {
+            positions
+        })
+    };
+
+    if args.format == Format::Ndjson && args.extract.fields.is_none() {
+        bail!("--format ndjson selects keys with -f");
+    }
+    if args.format == Format::Ndjson && args.complement {
+        bail!("--complement does not apply to --format ndjson");
+    }
+    if args.format != Format::Ndjson && args.output_format.is_some() {
+        bail!("--output-format only applies to --format ndjson");
+    }
+
+    let extract = if let Some(keys) = args
+        .extract
+        .fields
+        .as_deref()
+        .filter(|_| args.format == Format::Ndjson)
+    {
+        Extract::KeyPaths(parse_key_paths(keys)?)
+    } else if let Some(fields) = args.extract.fields.map(select).transpose()?
     // Transposes an Option of a Result into a Result of an Option
     {
         Extract::Fields(fields)
-    } else if let Some(bytes) = args.extract.bytes.map(parse_pos).transpose()? {
+    } else if let Some(names) = args.extract.field_names {
+        Extract::FieldNames(names.split(',').map(String::from).collect())
+    } else if let Some(bytes) = args.extract.bytes.map(select).transpose()? {
         Extract::Bytes(bytes)
-    } else if let Some(chars) = args.extract.chars.map(parse_pos).transpose()? {
+    } else if let Some(chars) = args.extract.chars.map(select).transpose()? {
         Extract::Chars(chars)
     } else {
-        unreachable!("Must have --fields, --bytes, or --chars");
+        unreachable!("Must have --fields, --field-names, --bytes, or --chars");
     };
 
+    let has_header = args.header || matches!(extract, Extract::FieldNames(_));
+    let mut header_written = false;
     for filename in args.files {
         match open(&filename) {
             Err(err) => eprint!("{filename}: {err}"),
             Ok(file) => {
                 match &extract {
-                    Extract::Fields(field_pos) => {
-                        let mut reader = ReaderBuilder::new()
-                            .delimiter(delimiter)
-                            .has_headers(false)
-                            .from_reader(file);
+                    Extract::Fields(_) | Extract::FieldNames(_) => {
+                        let mut records: Box<dyn Iterator<Item = Result<ByteRecord>>> =
+                            match csv_delimiter {
+                                None => Box::new(
+                                    file.split(b'\n').map(|line| Ok(splitter.split(&line?))),
+                                ),
+                                Some(delimiter) => Box::new(
+                                    ReaderBuilder::new()
+                                        .delimiter(delimiter)
+                                        .quote(quote)
+                                        .escape(escape)
+                                        .double_quote(escape.is_none())
+                                        .has_headers(false)
+                                        .flexible(args.flexible.unwrap_or(true))
+                                        .from_reader(file)
+                                        .into_byte_records()
+                                        .map(|record| Ok(record?)),
+                                ),
+                            };
 
                         let mut wtr = WriterBuilder::new()
-                            .delimiter(delimiter)
+                            .delimiter(output_byte)
+                            .quote(quote)
+                            .escape(escape.unwrap_or(b'\\'))
+                            .double_quote(escape.is_none())
+                            .flexible(true)
                             .from_writer(io::stdout());
+                        let mut write = |fields: Vec<&[u8]>| -> Result<()> {
+                            match joiner {
+                                Some(joiner) => io::stdout()
+                                    .write_all(&[&fields.join(joiner), &b"\n"[..]].concat())?,
+                                None => wtr.write_record(fields)?,
+                            }
+                            Ok(())
+                        };
+
+                        let header = if has_header {
+                            match records.next() {
+                                Some(header) => Some(header?),
+                                None => continue,
+                            }
+                        } else {
+                            None
+                        };
+                        let field_pos = match &extract {
+                            Extract::FieldNames(names) => {
+                                // The header is there, an empty file was skipped
+                                let header = header.as_ref().unwrap();
+                                let positions = find_fields(header, names)
+                                    .map_err(|e| anyhow!("{filename}: {e}"))?;
+                                if args.complement {
+                                    complement_pos(&merge_pos(positions))
+                                } else {
+                                    positions
+                                }
+                            }
+                            Extract::Fields(field_pos) => field_pos.clone(),
+                            _ => unreachable!(),
+                        };
+                        if let Some(header) = header.filter(|_| !header_written) {
+                            write(extract_fields(&header, &field_pos))?;
+                            header_written = true;
+                        }
 
-                        for record in reader.records() {
-                            wtr.write_record(extract_fields(&record?, field_pos))?;
+                        for record in records {
+                            let record = record?;
+                            // A line without the delimiter is passed through whole
+                            // unless --only-delimited
+                            let fields = if record.len() > 1 {
+                                extract_fields(&record, &field_pos)
+                            } else if args.only_delimited {
+                                continue;
+                            } else {
+                                record.iter().collect()
+                            };
+                            write(fields)?;
                         }
                     },
                     Extract::Bytes(bytes) => {
-                        for line in file.lines() {
-                            println!("{}", extract_bytes(&line?, bytes));
+                        // The bytes are written as they are, even half a character
+                        for line in file.split(b'\n') {
+                            let mut selected = extract_bytes(&line?, bytes, args.no_split);
+                            selected.push(b'\n');
+                            io::stdout().write_all(&selected)?;
                         }
                     },
                     Extract::Chars(chars) => {
                         for line in file.lines() {
-                            println!("{}", extract_chars(&line?, chars))
+                            if args.graphemes {
+                                println!("{}", extract_graphemes(&line?, chars))
+                            } else {
+                                println!("{}", extract_chars(&line?, chars))
+                            }
+                        }
+                    },
+                    Extract::KeyPaths(key_paths) => {
+                        let output_format = args.output_format.unwrap_or(OutputFormat::Tsv);
+                        let mut wtr = WriterBuilder::new()
+                            .flexible(true)
+                            .from_writer(io::stdout());
+                        let mut write_row = |cells: Vec<String>| -> Result<()> {
+                            match output_format {
+                                OutputFormat::Csv => wtr.write_record(cells)?,
+                                _ => println!(
+                                    "{}",
+                                    cells
+                                        .iter()
+                                        .map(|cell| escape_tsv(cell))
+                                        .collect::<Vec<_>>()
+                                        .join("\t")
+                                ),
+                            }
+                            Ok(())
+                        };
+                        if args.header && !header_written && output_format != OutputFormat::Ndjson {
+                            write_row(
+                                key_paths
+                                    .iter()
+                                    .map(|key_path| key_path.name.clone())
+                                    .collect(),
+                            )?;
+                            header_written = true;
+                        }
+
+                        // Read as a stream of values, so errors point at the line in the file
+                        for record in
+                            serde_json::Deserializer::from_reader(file).into_iter::<Value>()
+                        {
+                            let record = record.map_err(|e| anyhow!("{filename}: {e}"))?;
+                            let values: Vec<_> = key_paths
+                                .iter()
+                                .map(|key_path| record.pointer(&key_path.pointer))
+                                .collect();
+                            match output_format {
+                                OutputFormat::Ndjson => {
+                                    println!("{}", json_object(key_paths, &values))
+                                }
+                                _ => write_row(values.into_iter().map(json_cell).collect())?,
+                            }
                         }
                     },
                 }
@@ -113,43 +450,169 @@ fn run(args: Args) -> Result<()> {
     Ok(())
 }
 
+/// Parse a list like `1,3-5,7-` into zero-based ranges, in the order given.
+/// `N-` runs to the end of the line and `-M` from its start.
 fn parse_pos(range: String) -> Result<PositionList> {
-    let range_re = Regex::new(r"^(\d+)-(\d+)$").unwrap(); // The parentheses values will be captured
+    let range_re = Regex::new(r"^(\d*)-(\d*)$").unwrap(); // The parentheses values will be captured
     range
         .split(',')
-        .into_iter()
         .map(|val| {
             parse_index(val).map(|n| n..n + 1).or_else(|e| {
-                range_re.captures(val).ok_or(e).and_then(|captures| {
-                    let n1 = parse_index(&captures[1])?;
-                    let n2 = parse_index(&captures[2])?;
-                    if n1 >= n2 {
-                        bail!(
-                            "First number in range ({}) \
-                            must be lower than second number ({})",
-                            n1 + 1,
-                            n2 + 1
-                        );
-                    }
-                    Ok(n1..n2 + 1)
-                })
+                range_re
+                    .captures(val)
+                    .filter(|captures| !captures[1].is_empty() || !captures[2].is_empty())
+                    .ok_or(e)
+                    .and_then(|captures| {
+                        let start = match &captures[1] {
+                            "" => None,
+                            n => Some(parse_index(n)?),
+                        };
+                        let end = match &captures[2] {
+                            "" => None,
+                            n => Some(parse_index(n)?),
+                        };
+                        match (start, end) {
+                            (Some(n1), Some(n2)) if n1 >= n2 => bail!(
+                                "First number in range ({}) \
+                                must be lower than second number ({})",
+                                n1 + 1,
+                                n2 + 1
+                            ),
+                            _ => Ok(start.unwrap_or(0)..end.map_or(usize::MAX, |n2| n2 + 1)),
+                        }
+                    })
             })
         })
-        .collect::<Result<_, _>>()
-        .map_err(From::from)
+        .collect()
 }
 
 fn parse_index(input: &str) -> Result<usize> {
     let value_error = || anyhow!(r#"illegal list value: "{input}""#);
-    input
-        .starts_with('+')
-        .then(|| Err(value_error()))
-        .unwrap_or_else(|| {
-            input
-                .parse::<NonZeroUsize>()
-                .map(|n| usize::from(n) - 1)
-                .map_err(|_| value_error())
+    if input.starts_with('+') {
+        Err(value_error())
+    } else {
+        input
+            .parse::<NonZeroUsize>()
+            .map(|n| usize::from(n) - 1)
+            .map_err(|_| value_error())
+    }
+}
+
+/// Parse a list like `name,/user/id` into the keys of each record, or
+/// JSON pointers into it for those starting with a slash
+fn parse_key_paths(list: &str) -> Result<Vec<KeyPath>> {
+    list.split(',')
+        .map(|name| {
+            let pointer = match name {
+                "" => bail!(r#"illegal key: "{name}""#),
+                _ if name.starts_with('/') => name.to_string(),
+                // Escaped as in RFC 6901
+                _ => format!("/{}", name.replace('~', "~0").replace('/', "~1")),
+            };
+            Ok(KeyPath {
+                name: name.to_string(),
+                pointer,
+            })
         })
+        .collect()
+}
+
+/// A selected JSON value as a TSV or CSV cell: strings as they are, null and
+/// missing values empty and anything else as JSON
+fn json_cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
+/// Keep a TSV cell on one line and in one column
+fn escape_tsv(cell: &str) -> String {
+    cell.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// The selected values as one NDJSON object, keyed as given on the command
+/// line and in that order. Missing values are null.
+fn json_object(key_paths: &[KeyPath], values: &[Option<&Value>]) -> String {
+    let members: Vec<_> = key_paths
+        .iter()
+        .zip(values)
+        .map(|(key_path, value)| {
+            format!(
+                "{}:{}",
+                Value::from(key_path.name.as_str()),
+                value.unwrap_or(&Value::Null)
+            )
+        })
+        .collect();
+    format!("{{{}}}", members.join(","))
+}
+
+/// The position of each named field in the header, in the order given
+fn find_fields(header: &ByteRecord, names: &[String]) -> Result<PositionList> {
+    names
+        .iter()
+        .map(|name| {
+            let mut found = header
+                .iter()
+                .enumerate()
+                .filter(|(_, field)| *field == name.as_bytes());
+            match (found.next(), found.next()) {
+                (Some((i, _)), None) => Ok(i..i + 1),
+                (Some(_), Some(_)) => {
+                    bail!(r#"field "{name}" appears more than once in the header"#)
+                }
+                (None, _) => bail!(r#"unknown field "{name}""#),
+            }
+        })
+        .collect()
+}
+
+/// Sort the ranges and merge the ones that overlap or touch, so that every
+/// position is selected once and in the order of the line, like GNU cut
+fn merge_pos(mut positions: PositionList) -> PositionList {
+    positions.sort_by_key(|range| range.start);
+    let mut merged: PositionList = vec![];
+    for range in positions {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Every position outside of the sorted and merged ranges, for --complement
+fn complement_pos(positions: &[Range<usize>]) -> PositionList {
+    let mut complement = vec![];
+    let mut start = 0;
+    for range in positions {
+        if start < range.start {
+            complement.push(start..range.start);
+        }
+        start = range.end;
+    }
+    if start < usize::MAX {
+        complement.push(start..usize::MAX);
+    }
+    complement
+}
+
+/// The part of a range that falls inside a line of `len` positions
+fn clamp(range: &Range<usize>, len: usize) -> Range<usize> {
+    range.start.min(len)..range.end.min(len)
+}
+
+/// An option that has to be exactly one byte
+fn single_byte(option: &str, value: &str) -> Result<u8> {
+    match value.as_bytes() {
+        [byte] => Ok(*byte),
+        _ => bail!(r#"{option} "{value}" must be a single byte"#), // Raw string. Starts with r followed by 0 or more #, followed by ". " can be used inside it without escaping \"
+    }
 }
 
 fn open(filename: &str) -> Result<Box<dyn BufRead>> {
@@ -184,27 +647,55 @@ fn extract_chars(line: &str, char_pos: &[Range<usize>]) -> String {
 
     char_pos
         .iter()
-        .cloned()
-        .flat_map(|range| range.filter_map(|i| chars.get(i))) // flat map combibes map and flatten
+        .flat_map(|range| &chars[clamp(range, chars.len())]) // flat map combibes map and flatten
         .collect()
 }
 
-fn extract_bytes(line: &str, byte_pos: &[Range<usize>]) -> String {
-    let bytes = line.as_bytes();
-    let selected: Vec<_> = byte_pos
+fn extract_graphemes(line: &str, grapheme_pos: &[Range<usize>]) -> String {
+    let graphemes: Vec<_> = line.graphemes(true).collect();
+    grapheme_pos
         .iter()
-        .cloned()
-        .flat_map(|range| range.filter_map(|i| bytes.get(i)).copied()) // get returns a byte reference (&u8). We need copied to convert (create a copy of the element) to a byte (u8) as String::from_utf16_lossy expectes a slice of bytes, not byte references
-        .collect();
-    String::from_utf8_lossy(&selected).into_owned() // Use Cow::into_owned to clone the data, if needed
+        .flat_map(|range| &graphemes[clamp(range, graphemes.len())])
+        .copied()
+        .collect()
 }
 
-fn extract_fields(record: &StringRecord, field_pos: &[Range<usize>]) -> Vec<String> {
+fn extract_bytes(line: &[u8], byte_pos: &[Range<usize>], no_split: bool) -> Vec<u8> {
+    byte_pos
+        .iter()
+        .flat_map(|range| {
+            let range = clamp(range, line.len());
+            let range = if no_split {
+                align_to_chars(line, range)
+            } else {
+                range
+            };
+            &line[range]
+        })
+        .copied()
+        .collect()
+}
+
+/// Move both ends of a byte range back to the start of the character they
+/// fall in, so the first character is taken whole and the last one only if
+/// it is complete, like POSIX `cut -n`
+fn align_to_chars(line: &[u8], range: Range<usize>) -> Range<usize> {
+    // UTF-8 continuation bytes look like 0b10xxxxxx
+    let starts_char = |i: usize| line.get(i).is_none_or(|&byte| byte & 0xC0 != 0x80);
+    let back = |mut i: usize| {
+        while i > 0 && !starts_char(i) {
+            i -= 1;
+        }
+        i
+    };
+    let start = back(range.start);
+    start..back(range.end).max(start)
+}
+
+fn extract_fields<'a>(record: &'a ByteRecord, field_pos: &[Range<usize>]) -> Vec<&'a [u8]> {
     field_pos
         .iter()
-        .cloned()
-        .flat_map(|range| range.filter_map(|i| record.get(i)))
-        .map(String::from)
+        .flat_map(|range| clamp(range, record.len()).filter_map(|i| record.get(i)))
         .collect()
 }
 
@@ -215,8 +706,7 @@ fn _extract_fields<'a>(
 ) -> Vec<&'a str> {
     field_pos
         .iter()
-        .cloned()
-        .flat_map(|range| range.filter_map(|i| record.get(i)))
+        .flat_map(|range| clamp(range, record.len()).filter_map(|i| record.get(i)))
         .collect()
 }
 
@@ -289,33 +779,144 @@ fn _get_args() -> Args {
                 .value_name("DELIMITER")
                 .short('d')
                 .long("delim")
-                .help("Field delimiter")
+                .help("Field delimiter, any string")
                 .default_value("\t"),
         )
+        .arg(
+            Arg::new("regex_delimiter")
+                .value_name("REGEX")
+                .long("regex-delimiter")
+                .help("Split fields on matches of a regular expression, like \\s+")
+                .conflicts_with_all(["delimiter", "whitespace", "bytes", "chars"]),
+        )
+        .arg(
+            Arg::new("whitespace")
+                .short('w')
+                .long("whitespace")
+                .help("Split fields on runs of spaces and tabs")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["delimiter", "bytes", "chars"]),
+        )
+        .arg(
+            Arg::new("output_delimiter")
+                .value_name("STRING")
+                .long("output-delimiter")
+                .help("Delimiter written between the selected fields")
+                .conflicts_with_all(["bytes", "chars"]),
+        )
+        .arg(
+            Arg::new("only_delimited")
+                .short('s')
+                .long("only-delimited")
+                .help("Skip lines that contain no delimiter")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["bytes", "chars"]),
+        )
+        .arg(
+            Arg::new("graphemes")
+                .long("graphemes")
+                .help("Count -c positions in user-perceived characters")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["fields", "field_names", "bytes"]),
+        )
+        .arg(
+            Arg::new("no_split")
+                .short('n')
+                .help("Do not split multi-byte characters with -b")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["fields", "field_names", "chars"]),
+        )
+        .arg(
+            Arg::new("complement")
+                .long("complement")
+                .help("Select everything except the listed positions")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("header")
+                .long("header")
+                .help("Treat the first line of each file as a header")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["bytes", "chars"]),
+        )
+        .arg(
+            Arg::new("format")
+                .value_name("FORMAT")
+                .long("format")
+                .help("Input format, ndjson selects -f keys or JSON pointers")
+                .value_parser(value_parser!(Format))
+                .default_value("delimited"),
+        )
+        .arg(
+            Arg::new("output_format")
+                .value_name("FORMAT")
+                .long("output-format")
+                .help("How --format ndjson writes the selected values")
+                .value_parser(value_parser!(OutputFormat)),
+        )
+        .arg(
+            Arg::new("mode")
+                .value_name("MODE")
+                .long("mode")
+                .help("How fields are read")
+                .value_parser(value_parser!(Mode)),
+        )
+        .arg(
+            Arg::new("quote")
+                .value_name("CHAR")
+                .long("quote")
+                .help("Quote character for --mode csv [default: \"]"),
+        )
+        .arg(
+            Arg::new("escape")
+                .value_name("CHAR")
+                .long("escape")
+                .help("Escape character for quotes inside quoted fields in --mode csv"),
+        )
+        .arg(
+            Arg::new("flexible")
+                .value_name("BOOL")
+                .long("flexible")
+                .help("Allow records of different lengths in --mode csv, or not with --flexible=false")
+                .value_parser(value_parser!(bool))
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("true"),
+        )
         .arg(
             Arg::new("fields")
                 .value_name("FIELDS")
                 .short('f')
                 .long("fields")
-                .help("Selected fields"),
+                .help("Selected fields, like 1,3-5 or 7-")
+                .allow_hyphen_values(true),
+        )
+        .arg(
+            Arg::new("field_names")
+                .value_name("NAMES")
+                .short('F')
+                .long("field-names")
+                .help("Selected fields by header name, like name,email,id"),
         )
         .arg(
             Arg::new("bytes")
                 .value_name("BYTES")
                 .short('b')
                 .long("bytes")
-                .help("Selected bytes"),
+                .help("Selected bytes, like 1,3-5 or 7-")
+                .allow_hyphen_values(true),
         )
         .arg(
             Arg::new("chars")
                 .value_name("CHARS")
                 .short('c')
                 .long("chars")
-                .help("Selected characters"),
+                .help("Selected characters, like 1,3-5 or 7-")
+                .allow_hyphen_values(true),
         )
         .group(
             ArgGroup::new("extract")
-                .args(["fields", "bytes", "chars"])
+                .args(["fields", "field_names", "bytes", "chars"])
                 .required(true)
                 .multiple(false),
         )
@@ -323,8 +924,23 @@ fn _get_args() -> Args {
     Args {
         files: matches.get_many("files").unwrap().cloned().collect(),
         delimiter: matches.get_one("delimiter").cloned().unwrap(),
+        regex_delimiter: matches.get_one("regex_delimiter").cloned(),
+        whitespace: matches.get_flag("whitespace"),
+        output_delimiter: matches.get_one("output_delimiter").cloned(),
+        only_delimited: matches.get_flag("only_delimited"),
+        graphemes: matches.get_flag("graphemes"),
+        no_split: matches.get_flag("no_split"),
+        complement: matches.get_flag("complement"),
+        header: matches.get_flag("header"),
+        format: *matches.get_one("format").unwrap(),
+        output_format: matches.get_one("output_format").copied(),
+        mode: matches.get_one("mode").copied(),
+        quote: matches.get_one("quote").cloned(),
+        escape: matches.get_one("escape").cloned(),
+        flexible: matches.get_one("flexible").copied(),
         extract: ArgsExtract {
             fields: matches.get_one("fields").cloned(),
+            field_names: matches.get_one("field_names").cloned(),
             bytes: matches.get_one("bytes").cloned(),
             chars: matches.get_one("chars").cloned(),
         },
@@ -332,12 +948,20 @@ fn _get_args() -> Args {
 }
 
 #[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
 mod unit_tests {
-    use csv::StringRecord;
+    use std::ops::Range;
+
+    use csv::ByteRecord;
 
     use crate::extract_fields;
 
-    use super::{extract_bytes, extract_chars, parse_pos};
+    use serde_json::json;
+
+    use super::{
+        complement_pos, escape_tsv, extract_bytes, extract_chars, extract_graphemes, find_fields,
+        json_cell, json_object, merge_pos, parse_key_paths, parse_pos, KeyPath, Splitter,
+    };
 
     #[test]
     fn test_parse_pos() {
@@ -399,9 +1023,6 @@ mod unit_tests {
         let res = parse_pos("1,".to_string());
         assert!(res.is_err());
 
-        let res = parse_pos("1-".to_string());
-        assert!(res.is_err());
-
         let res = parse_pos("1-1-1".to_string());
         assert!(res.is_err());
 
@@ -455,6 +1076,147 @@ mod unit_tests {
         let res = parse_pos("15,19-20".to_string());
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), vec![14..15, 18..20]);
+
+        // Open-ended ranges run to the end or from the start of the line
+        let res = parse_pos("3-".to_string());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![2..usize::MAX]);
+
+        let res = parse_pos("-3".to_string());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![0..3]);
+
+        let res = parse_pos("-2,5-".to_string());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![0..2, 4..usize::MAX]);
+
+        let res = parse_pos("-0".to_string());
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), r#"illegal list value: "0""#);
+    }
+
+    #[test]
+    fn test_find_fields() {
+        let header = ByteRecord::from(vec!["name", "email", "id", "email"]);
+        let names = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let res = find_fields(&header, &names(&["id", "name"]));
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![2..3, 0..1]);
+
+        let res = find_fields(&header, &names(&["phone"]));
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), r#"unknown field "phone""#);
+
+        let res = find_fields(&header, &names(&["email"]));
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            r#"field "email" appears more than once in the header"#
+        );
+    }
+
+    #[test]
+    fn test_splitter() {
+        let fields = |splitter: Splitter, line: &str| -> Vec<String> {
+            splitter
+                .split(line.as_bytes())
+                .iter()
+                .map(|field| String::from_utf8_lossy(field).into_owned())
+                .collect()
+        };
+
+        assert_eq!(
+            fields(Splitter::Byte(b','), "a,\"b,c\""),
+            &["a", "\"b", "c\""]
+        );
+        assert_eq!(fields(Splitter::Byte(b','), ""), &[""]);
+
+        let colons = regex::bytes::Regex::new("::").unwrap();
+        assert_eq!(
+            fields(Splitter::Pattern(colons), "a::b:c::"),
+            &["a", "b:c", ""]
+        );
+
+        let digits = regex::bytes::Regex::new(r"\d+").unwrap();
+        assert_eq!(
+            fields(Splitter::Pattern(digits), "a1b22c"),
+            &["a", "b", "c"]
+        );
+
+        assert_eq!(
+            fields(Splitter::Whitespace, " \t a  b\tc "),
+            &["a", "b", "c"]
+        );
+        assert_eq!(fields(Splitter::Whitespace, "   "), &["   "]);
+        assert_eq!(fields(Splitter::Whitespace, " a\t"), &[" a\t"]);
+    }
+
+    #[test]
+    fn test_parse_key_paths() {
+        let res = parse_key_paths("name,/user/id,a/b,~x");
+        assert!(res.is_ok());
+        let pointers: Vec<_> = res.unwrap().into_iter().map(|k| k.pointer).collect();
+        assert_eq!(pointers, &["/name", "/user/id", "/a~1b", "/~0x"]);
+
+        let res = parse_key_paths("name,");
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), r#"illegal key: """#);
+    }
+
+    #[test]
+    fn test_json_cell() {
+        assert_eq!(json_cell(None), "");
+        assert_eq!(json_cell(Some(&json!(null))), "");
+        assert_eq!(json_cell(Some(&json!("a\"b"))), "a\"b");
+        assert_eq!(json_cell(Some(&json!(1.5))), "1.5");
+        assert_eq!(json_cell(Some(&json!(true))), "true");
+        assert_eq!(json_cell(Some(&json!({"a": [1]}))), r#"{"a":[1]}"#);
+    }
+
+    #[test]
+    fn test_escape_tsv() {
+        assert_eq!(escape_tsv("plain"), "plain");
+        assert_eq!(escape_tsv("a\tb\nc\\"), r"a\tb\nc\\");
+    }
+
+    #[test]
+    fn test_json_object() {
+        let key_paths = parse_key_paths("b,/a/c").unwrap();
+        let value = json!("x");
+        assert_eq!(
+            json_object(&key_paths, &[Some(&value), None]),
+            r#"{"b":"x","/a/c":null}"#
+        );
+        assert_eq!(
+            key_paths[1],
+            KeyPath {
+                name: "/a/c".to_string(),
+                pointer: "/a/c".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_merge_pos() {
+        assert!(merge_pos(vec![]).is_empty());
+        assert_eq!(merge_pos(vec![6..7, 0..1, 2..5]), vec![0..1, 2..5, 6..7]);
+        assert_eq!(merge_pos(vec![0..1, 0..1]), vec![0..1]);
+        assert_eq!(merge_pos(vec![0..3, 1..2]), vec![0..3]);
+        assert_eq!(merge_pos(vec![2..4, 0..3, 4..5]), vec![0..5]);
+        assert_eq!(merge_pos(vec![4..usize::MAX, 0..6]), vec![0..usize::MAX]);
+    }
+
+    #[test]
+    fn test_complement_pos() {
+        assert_eq!(complement_pos(&[]), vec![0..usize::MAX]);
+        assert_eq!(complement_pos(&[0..1]), vec![1..usize::MAX]);
+        assert_eq!(
+            complement_pos(&[1..2, 4..5]),
+            vec![0..1, 2..4, 5..usize::MAX]
+        );
+        assert_eq!(complement_pos(&[2..usize::MAX]), vec![0..2]);
+        assert!(complement_pos(&[0..usize::MAX]).is_empty());
     }
 
     #[test]
@@ -465,25 +1227,62 @@ mod unit_tests {
         assert_eq!(extract_chars("ábc", &[0..3]), "ábc".to_string());
         assert_eq!(extract_chars("ábc", &[2..3, 1..2]), "cb".to_string());
         assert_eq!(extract_chars("ábc", &[0..1, 1..2, 4..5]), "áb".to_string());
+        assert_eq!(extract_chars("ábc", &[1..usize::MAX]), "bc".to_string());
     }
 
     #[test]
     fn test_extract_bytes() {
-        assert_eq!(extract_bytes("ábc", &[0..1]), "�".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..2]), "á".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..3]), "áb".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..4]), "ábc".to_string());
-        assert_eq!(extract_bytes("ábc", &[3..4, 2..3]), "cb".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..2, 5..6]), "á".to_string());
+        let line = "ábc".as_bytes();
+        assert_eq!(extract_bytes(line, &[0..1], false), b"\xc3");
+        assert_eq!(extract_bytes(line, &[0..2], false), "á".as_bytes());
+        assert_eq!(extract_bytes(line, &[0..3], false), "áb".as_bytes());
+        assert_eq!(extract_bytes(line, &[0..4], false), "ábc".as_bytes());
+        assert_eq!(extract_bytes(line, &[3..4, 2..3], false), b"cb");
+        assert_eq!(extract_bytes(line, &[0..2, 5..6], false), "á".as_bytes());
+        assert_eq!(extract_bytes(line, &[1..3], false), b"\xa1b");
+
+        // Without splitting, a cut character is taken from its start and
+        // dropped at the end
+        assert_eq!(extract_bytes(line, &[0..1], true), b"");
+        assert_eq!(extract_bytes(line, &[1..3], true), "áb".as_bytes());
+        assert_eq!(extract_bytes(line, &[1..2], true), "á".as_bytes());
+        assert_eq!(
+            extract_bytes(line, &[0..usize::MAX], true),
+            "ábc".as_bytes()
+        );
+        assert_eq!(extract_bytes(b"\xa1\xa1b", &[0..3], true), b"\xa1\xa1b");
+    }
+
+    #[test]
+    fn test_extract_graphemes() {
+        assert_eq!(extract_graphemes("", &[0..1]), "".to_string());
+        assert_eq!(
+            extract_graphemes("e\u{301}tat", &[0..1]),
+            "e\u{301}".to_string()
+        );
+        assert_eq!(extract_graphemes("e\u{301}tat", &[1..3]), "ta".to_string());
+        let family = "\u{1F469}\u{200D}\u{1F467}";
+        assert_eq!(extract_graphemes(&format!("{family}!"), &[0..1]), family);
+        assert_eq!(
+            extract_graphemes(&format!("{family}!"), &[1..usize::MAX]),
+            "!"
+        );
     }
 
     #[test]
     fn test_extract_fields() {
-        let rec = StringRecord::from(vec!["Captain", "Sham", "12345"]);
-        assert_eq!(extract_fields(&rec, &[0..1]), &["Captain"]);
-        assert_eq!(extract_fields(&rec, &[1..2]), &["Sham"]);
-        assert_eq!(extract_fields(&rec, &[0..1, 2..3]), &["Captain", "12345"]);
-        assert_eq!(extract_fields(&rec, &[0..1, 3..4]), &["Captain"]);
-        assert_eq!(extract_fields(&rec, &[1..2, 0..1]), &["Sham", "Captain"]);
+        let rec = ByteRecord::from(vec!["Captain", "Sham", "12345"]);
+        let extract_fields = |field_pos: &[Range<usize>]| -> Vec<String> {
+            extract_fields(&rec, field_pos)
+                .iter()
+                .map(|field| String::from_utf8_lossy(field).into_owned())
+                .collect()
+        };
+        assert_eq!(extract_fields(&[0..1]), &["Captain"]);
+        assert_eq!(extract_fields(&[1..2]), &["Sham"]);
+        assert_eq!(extract_fields(&[0..1, 2..3]), &["Captain", "12345"]);
+        assert_eq!(extract_fields(&[0..1, 3..4]), &["Captain"]);
+        assert_eq!(extract_fields(&[1..2, 0..1]), &["Sham", "Captain"]);
+        assert_eq!(extract_fields(&[1..usize::MAX]), &["Sham", "12345"]);
     }
 }
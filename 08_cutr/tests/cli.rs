@@ -58,7 +58,7 @@ fn dies_not_enough_args() -> Result<()> {
     dies(
         &[CSV],
         "the following required arguments were not provided:\n  \
-        <--fields <FIELDS>|--bytes <BYTES>|--chars <CHARS>>",
+        <--fields <FIELDS>|--field-names <NAMES>|--bytes <BYTES>|--chars <CHARS>>",
     )
 }
 
@@ -97,7 +97,7 @@ fn dies_bad_digit_chars() -> Result<()> {
 fn dies_empty_delimiter() -> Result<()> {
     dies(
         &[CSV, "-f", "1", "-d", ""],
-        r#"--delim "" must be a single byte"#,
+        r#"--delim "" must not be empty"#,
     )
 }
 
@@ -105,8 +105,8 @@ fn dies_empty_delimiter() -> Result<()> {
 #[test]
 fn dies_bad_delimiter() -> Result<()> {
     dies(
-        &[CSV, "-f", "1", "-d", ",,"],
-        r#"--delim ",," must be a single byte"#,
+        &[CSV, "-f", "1", "-d", ",,", "--mode", "csv"],
+        r#"--delim ",," must be a single byte for --mode csv"#,
     )
 }
 
@@ -162,14 +162,11 @@ fn run(args: &[&str], expected_file: &str) -> Result<()> {
 }
 
 // --------------------------------------------------
-fn run_lossy(args: &[&str], expected_file: &str) -> Result<()> {
-    let contents = fs::read(expected_file)?;
-    let expected = String::from_utf8_lossy(&contents);
+fn run_bytes(args: &[&str], expected_file: &str) -> Result<()> {
+    let expected = fs::read(expected_file)?;
     let output = Command::cargo_bin(PRG)?.args(args).output().expect("fail");
     assert!(output.status.success());
-
-    let stdout = String::from_utf8(output.stdout).expect("invalid UTF-8");
-    assert_eq!(stdout, expected);
+    assert_eq!(output.stdout, expected);
     Ok(())
 }
 
@@ -278,7 +275,7 @@ fn tsv_b2() -> Result<()> {
 // --------------------------------------------------
 #[test]
 fn tsv_b8() -> Result<()> {
-    run_lossy(&[TSV, "-b", "8"], "tests/expected/movies1.tsv.b8.out")
+    run_bytes(&[TSV, "-b", "8"], "tests/expected/movies1.tsv.b8.out")
 }
 
 // --------------------------------------------------
@@ -296,7 +293,7 @@ fn tsv_b2_3() -> Result<()> {
 // --------------------------------------------------
 #[test]
 fn tsv_b1_8() -> Result<()> {
-    run_lossy(&[TSV, "-b", "1-8"], "tests/expected/movies1.tsv.b1-8.out")
+    run_bytes(&[TSV, "-b", "1-8"], "tests/expected/movies1.tsv.b1-8.out")
 }
 
 // --------------------------------------------------
@@ -340,3 +337,402 @@ fn tsv_c1_8() -> Result<()> {
 fn repeated_value() -> Result<()> {
     run(&[BOOKS, "-c", "1,1"], "tests/expected/books.c1,1.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn tsv_f2_open_end() -> Result<()> {
+    run(&[TSV, "-f", "2-"], "tests/expected/movies1.tsv.f2-.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_f2_open_start() -> Result<()> {
+    run(&[TSV, "-f", "-2"], "tests/expected/movies1.tsv.f-2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn fields_in_line_order() -> Result<()> {
+    run(&[TSV, "-f", "3,1"], "tests/expected/movies1.tsv.f3,1.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn overlapping_ranges() -> Result<()> {
+    run(
+        &[BOOKS, "-c", "1-3,2-5"],
+        "tests/expected/books.c1-3,2-5.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn chars_open_end() -> Result<()> {
+    run(&[BOOKS, "-c", "5-"], "tests/expected/books.c5-.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_open_end() -> Result<()> {
+    run(&[BOOKS, "-b", "3-"], "tests/expected/books.b3-.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn complement_fields() -> Result<()> {
+    run(
+        &[TSV, "-f", "2", "--complement"],
+        "tests/expected/movies1.tsv.f2.complement.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn complement_chars() -> Result<()> {
+    run(
+        &[BOOKS, "-c", "-5", "--complement"],
+        "tests/expected/books.c-5.complement.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bare_hyphen() -> Result<()> {
+    dies(&[TSV, "-f", "-"], r#"illegal list value: "-""#)
+}
+
+// --------------------------------------------------
+#[test]
+fn undelimited_lines_pass_through() -> Result<()> {
+    run(
+        &["tests/inputs/movies3.tsv", "-f", "1,3"],
+        "tests/expected/movies3.tsv.f1,3.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn only_delimited() -> Result<()> {
+    run(
+        &["tests/inputs/movies3.tsv", "-f", "1,3", "-s"],
+        "tests/expected/movies3.tsv.f1,3.s.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn multibyte_output_delimiter() -> Result<()> {
+    run(
+        &[TSV, "-f", "1-3", "--output-delimiter", " :: "],
+        "tests/expected/movies1.tsv.f1-3.od.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn output_delimiter_quotes_fields() -> Result<()> {
+    run(
+        &[
+            "tests/inputs/movies2.tsv",
+            "-f",
+            "1,2",
+            "--output-delimiter",
+            ",",
+        ],
+        "tests/expected/movies2.tsv.f1-2.odcomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_only_delimited_chars() -> Result<()> {
+    dies(
+        &[TSV, "-c", "1", "-s"],
+        "'--chars <CHARS>' cannot be used with '--only-delimited'",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn field_names_follow_each_header() -> Result<()> {
+    run(
+        &[TSV, "tests/inputs/movies4.tsv", "-F", "title,director"],
+        "tests/expected/movies1_4.tsv.Ftitle,director.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn field_names_complement() -> Result<()> {
+    run(
+        &[
+            TSV,
+            "tests/inputs/movies4.tsv",
+            "-F",
+            "year",
+            "--complement",
+        ],
+        "tests/expected/movies1_4.tsv.Fyear.complement.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn header_written_once() -> Result<()> {
+    run(
+        &[TSV, TSV, "-f", "1", "--header"],
+        "tests/expected/movies1.tsv.f1.header.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_unknown_field_name() -> Result<()> {
+    dies(
+        &[TSV, "-F", "title,rating"],
+        r#"tests/inputs/movies1.tsv: unknown field "rating""#,
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_duplicate_header() -> Result<()> {
+    dies(
+        &["tests/inputs/duplicate_headers.tsv", "-F", "title"],
+        r#"field "title" appears more than once in the header"#,
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn raw_mode_keeps_quotes() -> Result<()> {
+    run(
+        &["tests/inputs/quotes.tsv", "-f", "2", "--mode", "raw"],
+        "tests/expected/quotes.tsv.f2.raw.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_quote_char() -> Result<()> {
+    run(
+        &[
+            "tests/inputs/movies.quote.csv",
+            "-d",
+            ",",
+            "-f",
+            "2",
+            "--quote",
+            "'",
+        ],
+        "tests/expected/movies.quote.csv.f2.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_escape_char() -> Result<()> {
+    run(
+        &[
+            "tests/inputs/movies.escape.csv",
+            "-d",
+            ",",
+            "-f",
+            "1,2",
+            "--escape",
+            "\\",
+            "--output-delimiter",
+            " | ",
+        ],
+        "tests/expected/movies.escape.csv.f1-2.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_csv_uneven_records() -> Result<()> {
+    dies(
+        &["tests/inputs/movies2.tsv", "-f", "1", "--flexible=false"],
+        "found record with 3 fields, but the previous record has 2 fields",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_raw_with_quote() -> Result<()> {
+    dies(
+        &[TSV, "-f", "1", "--mode", "raw", "--quote", "'"],
+        "--quote, --escape and --flexible only apply to --mode csv",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_quote() -> Result<()> {
+    dies(
+        &[CSV, "-f", "1", "--quote", "''"],
+        r#"--quote "''" must be a single byte"#,
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn string_delimiter() -> Result<()> {
+    run(
+        &["tests/inputs/movies1.colons.txt", "-d", "::", "-f", "3,1"],
+        "tests/expected/movies1.colons.txt.f1,3.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn regex_delimiter() -> Result<()> {
+    run(
+        &[
+            "tests/inputs/ps.txt",
+            "--regex-delimiter",
+            r"\s+",
+            "-f",
+            "2,5",
+        ],
+        "tests/expected/ps.txt.f2,5.regex.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn whitespace_delimiter() -> Result<()> {
+    run(
+        &["tests/inputs/ps.txt", "-w", "-f", "1,4-"],
+        "tests/expected/ps.txt.f1,4-.w.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn whitespace_pass_through_keeps_blanks() -> Result<()> {
+    run(
+        &["tests/inputs/blanks.txt", "-w", "-f", "2"],
+        "tests/expected/blanks.txt.f2.w.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_regex_delimiter_matches_empty() -> Result<()> {
+    dies(
+        &[TSV, "--regex-delimiter", "x*", "-f", "1"],
+        r#"--regex-delimiter "x*" must not match an empty string"#,
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_whitespace_csv_mode() -> Result<()> {
+    dies(
+        &[TSV, "-w", "-f", "1", "--mode", "csv"],
+        "--regex-delimiter and -w only apply to --mode raw",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn chars_split_graphemes() -> Result<()> {
+    run(
+        &["tests/inputs/graphemes.txt", "-c", "1-2"],
+        "tests/expected/graphemes.txt.c1-2.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn graphemes() -> Result<()> {
+    run(
+        &["tests/inputs/graphemes.txt", "-c", "1-2", "--graphemes"],
+        "tests/expected/graphemes.txt.c1-2.graphemes.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_no_split() -> Result<()> {
+    run(
+        &[BOOKS, "-b", "2-3", "-n"],
+        "tests/expected/books.b2-3.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_graphemes_bytes() -> Result<()> {
+    dies(
+        &[BOOKS, "-b", "1", "--graphemes"],
+        "'--bytes <BYTES>' cannot be used with '--graphemes'",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn ndjson_to_tsv() -> Result<()> {
+    run(
+        &[
+            "tests/inputs/log.ndjson",
+            "--format",
+            "ndjson",
+            "-f",
+            "level,/user/id,msg",
+            "--header",
+        ],
+        "tests/expected/log.ndjson.tsv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn ndjson_to_csv() -> Result<()> {
+    run(
+        &[
+            "tests/inputs/log.ndjson",
+            "--format",
+            "ndjson",
+            "-f",
+            "level,/user/name,tags,a/b",
+            "--output-format",
+            "csv",
+            "--header",
+        ],
+        "tests/expected/log.ndjson.csv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn ndjson_to_ndjson() -> Result<()> {
+    run(
+        &[
+            "tests/inputs/log.ndjson",
+            "--format",
+            "ndjson",
+            "-f",
+            "/user/id,level,missing",
+            "--output-format",
+            "ndjson",
+        ],
+        "tests/expected/log.ndjson.ndjson.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_ndjson() -> Result<()> {
+    dies(
+        &[
+            "tests/inputs/bad.ndjson",
+            "--format",
+            "ndjson",
+            "-f",
+            "level",
+        ],
+        "tests/inputs/bad.ndjson: expected ident at line 2 column 2",
+    )
+}
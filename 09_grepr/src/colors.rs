@@ -0,0 +1,174 @@
+use std::ops::Range;
+
+use ansi_term::{Colour, Style};
+
+/// The styles `--color` paints with, named after their `GREP_COLORS`
+/// capabilities
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Colors {
+    /// `ms`: matches in selected lines
+    pub selected_match: Style,
+    /// `mc`: matches in context lines
+    pub context_match: Style,
+    /// `sl`: the rest of selected lines
+    pub selected_line: Style,
+    /// `cx`: the rest of context lines
+    pub context_line: Style,
+    /// `fn`: file names
+    pub filename: Style,
+    /// `ln`: line numbers, and columns for --column
+    pub line_number: Style,
+    /// `bn`: byte offsets
+    pub byte_offset: Style,
+    /// `se`: the separators after each field and `--` between groups
+    pub separator: Style,
+}
+
+impl Default for Colors {
+    /// The colors grep uses when `GREP_COLORS` is not set
+    fn default() -> Self {
+        Colors {
+            selected_match: Colour::Red.bold(),
+            context_match: Colour::Red.bold(),
+            selected_line: Style::new(),
+            context_line: Style::new(),
+            filename: Colour::Purple.normal(),
+            line_number: Colour::Green.normal(),
+            byte_offset: Colour::Green.normal(),
+            separator: Colour::Cyan.normal(),
+        }
+    }
+}
+
+impl Colors {
+    /// Styles that paint nothing, for when colors are off
+    pub fn plain() -> Self {
+        Colors {
+            selected_match: Style::new(),
+            context_match: Style::new(),
+            selected_line: Style::new(),
+            context_line: Style::new(),
+            filename: Style::new(),
+            line_number: Style::new(),
+            byte_offset: Style::new(),
+            separator: Style::new(),
+        }
+    }
+
+    /// The default colors with those in a `GREP_COLORS` value such as
+    /// `ms=01;32:fn=34` over them. `mt` sets both kinds of match. Like grep,
+    /// capabilities it does not know or cannot read are skipped.
+    pub fn parse(grep_colors: &str) -> Self {
+        let mut colors = Colors::default();
+        for capability in grep_colors.split(':') {
+            // Boolean capabilities like `rv` and `ne` change nothing here
+            let Some((name, value)) = capability.split_once('=') else {
+                continue;
+            };
+            let Some(style) = parse_sgr(value) else {
+                continue;
+            };
+            match name {
+                "mt" => {
+                    colors.selected_match = style;
+                    colors.context_match = style;
+                }
+                "ms" => colors.selected_match = style,
+                "mc" => colors.context_match = style,
+                "sl" => colors.selected_line = style,
+                "cx" => colors.context_line = style,
+                "fn" => colors.filename = style,
+                "ln" => colors.line_number = style,
+                "bn" => colors.byte_offset = style,
+                "se" => colors.separator = style,
+                _ => {}
+            }
+        }
+        colors
+    }
+
+    /// A line with its `spans` painted as matches and the rest as the line,
+    /// keeping the line ending out of the colors
+    pub fn paint_line(&self, text: &str, spans: &[Range<usize>], selected: bool) -> String {
+        let (line_style, match_style) = if selected {
+            (self.selected_line, self.selected_match)
+        } else {
+            (self.context_line, self.context_match)
+        };
+        let (content, ending) = match text.strip_suffix('\n') {
+            Some(content) => (content, "\n"),
+            None => (text, ""),
+        };
+
+        let mut painted = String::new();
+        let mut end = 0;
+        for span in spans.iter().filter(|span| !span.is_empty()) {
+            if span.start > end {
+                painted += &line_style.paint(&content[end..span.start]).to_string();
+            }
+            painted += &match_style.paint(&content[span.clone()]).to_string();
+            end = span.end;
+        }
+        if end < content.len() {
+            painted += &line_style.paint(&content[end..]).to_string();
+        }
+        painted + ending
+    }
+}
+
+/// Read the SGR parameters of an escape sequence, such as `01;31` for bold
+/// red, into a style. An empty value is no style at all.
+fn parse_sgr(sgr: &str) -> Option<Style> {
+    let mut style = Style::new();
+    if sgr.is_empty() {
+        return Some(style);
+    }
+
+    let mut codes = sgr.split(';').map(|code| code.parse::<u8>().ok());
+    while let Some(code) = codes.next() {
+        match code? {
+            0 => style = Style::new(),
+            1 => style = style.bold(),
+            2 => style = style.dimmed(),
+            3 => style = style.italic(),
+            4 => style = style.underline(),
+            5 => style = style.blink(),
+            7 => style = style.reverse(),
+            8 => style = style.hidden(),
+            9 => style = style.strikethrough(),
+            n @ 30..=37 => style.foreground = Some(basic(n - 30)),
+            38 => style.foreground = Some(extended(&mut codes)?),
+            39 => style.foreground = None,
+            n @ 40..=47 => style.background = Some(basic(n - 40)),
+            48 => style.background = Some(extended(&mut codes)?),
+            49 => style.background = None,
+            // The bright colors are the second eight of the 256
+            n @ 90..=97 => style.foreground = Some(Colour::Fixed(n - 90 + 8)),
+            n @ 100..=107 => style.background = Some(Colour::Fixed(n - 100 + 8)),
+            _ => return None,
+        }
+    }
+    Some(style)
+}
+
+fn basic(n: u8) -> Colour {
+    [
+        Colour::Black,
+        Colour::Red,
+        Colour::Green,
+        Colour::Yellow,
+        Colour::Blue,
+        Colour::Purple,
+        Colour::Cyan,
+        Colour::White,
+    ][n as usize]
+}
+
+/// The rest of a `38` or `48` code: `5;N` for one of 256 colors or `2;R;G;B`
+fn extended(codes: &mut impl Iterator<Item = Option<u8>>) -> Option<Colour> {
+    match codes.next()?? {
+        5 => Some(Colour::Fixed(codes.next()??)),
+        2 => Some(Colour::RGB(codes.next()??, codes.next()??, codes.next()??)),
+        _ => None,
+    }
+}
@@ -1,22 +1,39 @@
-use std::{fs::{self, File}, io::{self, BufRead, BufReader}, mem};
+mod colors;
+mod matcher;
+
+use std::{collections::VecDeque, env, error::Error, fmt::{self, Write as _}, fs::{self, File}, io::{self, BufRead, BufReader, IsTerminal, StdoutLock, Write}, mem, ops::Range};
 
 use anyhow::{anyhow, Result};
-use clap::{Arg, ArgAction, Command, Parser};
-use regex::{Regex, RegexBuilder};
-use walkdir::WalkDir;
+use clap::{Arg, ArgAction, Command, Parser, ValueEnum};
+use ignore::WalkBuilder;
+
+use colors::Colors;
+use matcher::{MatchOptions, Matcher};
 
 #[derive(Debug, Parser)]
-#[command(version, author, about)]
+#[command(version, author, about, disable_help_flag = true)]
 /// Rust version of `grep`
 struct Args {
-    /// Search patterm
-    #[arg(value_name = "PATTERN")]
-    pattern: String,
+    /// Search patterm, unless -e or -f give the patterns
+    #[arg(value_name = "PATTERN", required_unless_present_any = ["regexp", "file"])]
+    pattern: Option<String>,
 
-    /// Input file(s)
-    #[arg(value_name = "FILE", default_value = "-")]
+    /// Input file(s) [default: -]
+    #[arg(value_name = "FILE")]
     files: Vec<String>,
 
+    /// Search for PATTERN, given more than once to search for any of them
+    #[arg(long, short = 'e', value_name = "PATTERN", allow_hyphen_values = true)]
+    regexp: Vec<String>,
+
+    /// Read patterns from FILE, one per line
+    #[arg(long, short = 'f', value_name = "FILE")]
+    file: Vec<String>,
+
+    /// Take the patterns as strings to find as they are
+    #[arg(long = "fixed-strings", short = 'F')]
+    fixed_strings: bool,
+
     /// Case-insensitive
     #[arg(long, short)]
     insensitive: bool,
@@ -25,6 +42,16 @@ struct Args {
     #[arg(long, short)]
     recursive: bool,
 
+    /// With --recursive, skip files matched by .gitignore, .ignore,
+    /// .git/info/exclude and the global git excludes file, and hidden
+    /// files unless --hidden
+    #[arg(long = "respect-ignore")]
+    respect_ignore: bool,
+
+    /// Search hidden files with --respect-ignore
+    #[arg(long)]
+    hidden: bool,
+
     /// Count occurrences
     #[arg(long, short)]
     count: bool,
@@ -32,43 +59,311 @@ struct Args {
     /// Invert match
     #[arg(long = "invert-match", short = 'v')]
     invert: bool,
+
+    /// Print only the matches, each on its own line
+    #[arg(long = "only-matching", short = 'o')]
+    only_matching: bool,
+
+    /// Match only whole words
+    #[arg(long = "word-regexp", short = 'w')]
+    word_regexp: bool,
+
+    /// Match only whole lines
+    #[arg(long = "line-regexp", short = 'x')]
+    line_regexp: bool,
+
+    /// Stop reading a file after NUM matching lines
+    #[arg(long = "max-count", short = 'm', value_name = "NUM")]
+    max_count: Option<usize>,
+
+    /// Print nothing and stop at the first match, exiting with 1 if there is
+    /// none
+    #[arg(long, short = 'q', alias = "silent")]
+    quiet: bool,
+
+    /// Print only the names of files with a match
+    #[arg(long = "files-with-matches", short = 'l', conflicts_with = "files_without_match")]
+    files_with_matches: bool,
+
+    /// Print only the names of files without a match
+    #[arg(long = "files-without-match", short = 'L')]
+    files_without_match: bool,
+
+    /// Print NUM lines after each match
+    #[arg(long = "after-context", short = 'A', value_name = "NUM")]
+    after_context: Option<usize>,
+
+    /// Print NUM lines before each match
+    #[arg(long = "before-context", short = 'B', value_name = "NUM")]
+    before_context: Option<usize>,
+
+    /// Print NUM lines before and after each match, unless -A or -B say
+    /// otherwise
+    #[arg(long, short = 'C', value_name = "NUM")]
+    context: Option<usize>,
+
+    /// Print the line number before each line
+    #[arg(long = "line-number", short = 'n')]
+    line_number: bool,
+
+    /// Print the byte offset of each line in its file
+    #[arg(long = "byte-offset", short = 'b')]
+    byte_offset: bool,
+
+    /// Print the column of the first match on each line, implies -n
+    #[arg(long)]
+    column: bool,
+
+    /// Print the file name before each line, even for a single file
+    #[arg(long = "with-filename", short = 'H', overrides_with = "no_filename")]
+    with_filename: bool,
+
+    /// Never print file names
+    #[arg(long = "no-filename", short = 'h', overrides_with = "with_filename")]
+    no_filename: bool,
+
+    /// Name to print for standard input
+    #[arg(long, value_name = "LABEL", default_value = "(standard input)")]
+    label: String,
+
+    /// Highlight matches, file names, line numbers and separators, in the
+    /// colors set by $GREP_COLORS. Auto colors only a terminal, unless
+    /// $NO_COLOR is set.
+    #[arg(
+        long,
+        alias = "colour",
+        value_name = "WHEN",
+        value_enum,
+        default_value_t = ColorChoice::Auto,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "auto"
+    )]
+    color: ColorChoice,
+
+    /// Print help
+    #[arg(long, action = ArgAction::Help)]
+    help: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+/// How many lines to print around each match
+#[derive(Debug, Default, Clone, Copy)]
+struct Context {
+    before: usize,
+    after: usize,
+}
+
+impl Context {
+    fn is_empty(&self) -> bool {
+        self.before == 0 && self.after == 0
+    }
+}
+
+/// A line of output from `find_lines`
+#[derive(Debug, PartialEq, Eq)]
+enum Line {
+    /// A line that matches, or with --invert-match one that does not
+    Match(Text),
+    /// A line printed around a match for -A, -B or -C
+    Context(Text),
+    /// Lines were skipped between two groups of context, printed as `--`
+    Break,
+}
+
+/// A line read from a file, with where it is in the file and where the
+/// pattern matches it
+#[derive(Debug, PartialEq, Eq)]
+struct Text {
+    /// Counted from 1
+    number: usize,
+    /// The position of the first byte of the line, counted from 0
+    offset: usize,
+    text: String,
+    /// The byte ranges of `text` the pattern matches, left to right. The
+    /// line ending is never part of a match.
+    spans: Vec<Range<usize>>,
 }
 
 fn main() {
-    if let Err(e) = run(Args::parse()) {
-        eprintln!("{e}");
-        std::process::exit(1);
+    let args = Args::parse();
+    let quiet = args.quiet;
+    match run(args) {
+        // Whatever reads the output went away, as `head` does once it has
+        // enough
+        Err(e) if e.downcast_ref::<WriteError>().is_some_and(WriteError::broken_pipe) => {}
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        // Only -q tells with its exit status whether anything matched
+        Ok(matched) => {
+            if quiet && !matched {
+                std::process::exit(1);
+            }
+        }
     }
 }
 
-fn run(args: Args) -> Result<()> {
-    let pattern = RegexBuilder::new(&args.pattern)
-        .case_insensitive(args.insensitive)
-        .build()
-        .map_err(|_| anyhow!(r#"Invalid pattern "{}""#, args.pattern))?;
-
-    let entries = find_files(&args.files, args.recursive);
-    let num_files = entries.len();
-    let print = |fname: &str, val: &str| {
-        if num_files > 1 {
-            print!("{fname}:{val}")
+/// Search the files, printing as lines are found. Returns whether any line
+/// matched.
+fn run(args: Args) -> Result<bool> {
+    let mut patterns = args.regexp.clone();
+    for filename in &args.file {
+        let file = open(filename).map_err(|e| anyhow!("{filename}: {e}"))?;
+        for line in file.lines() {
+            patterns.push(line.map_err(|e| anyhow!("{filename}: {e}"))?);
+        }
+    }
+    // With -e or -f every positional argument is a file
+    let mut files = args.files.clone();
+    match &args.pattern {
+        Some(pattern) if args.regexp.is_empty() && args.file.is_empty() => {
+            patterns.push(pattern.clone())
+        }
+        Some(file) => files.insert(0, file.clone()),
+        None => {}
+    }
+    if files.is_empty() {
+        files.push("-".to_string());
+    }
+
+    let matcher = Matcher::new(
+        &patterns,
+        MatchOptions {
+            fixed_strings: args.fixed_strings,
+            insensitive: args.insensitive,
+            word: args.word_regexp,
+            line: args.line_regexp,
+        },
+    )?;
+
+    let entries = find_files(
+        &files,
+        args.recursive,
+        args.respect_ignore,
+        args.hidden,
+    );
+    let context = Context {
+        before: args.before_context.or(args.context).unwrap_or(0),
+        after: args.after_context.or(args.context).unwrap_or(0),
+    };
+
+    let colors = match args.color {
+        ColorChoice::Always => Colors::parse(&env::var("GREP_COLORS").unwrap_or_default()),
+        ColorChoice::Auto
+            if io::stdout().is_terminal()
+                && env::var_os("NO_COLOR").is_none_or(|no_color| no_color.is_empty()) =>
+        {
+            Colors::parse(&env::var("GREP_COLORS").unwrap_or_default())
+        }
+        _ => Colors::plain(),
+    };
+
+    let with_filename = args.with_filename || (!args.no_filename && entries.len() > 1);
+    let line_number = args.line_number || args.column;
+    // Like grep, ':' follows each field of a selected line and '-' those of a
+    // line of context
+    let prefix = |fname: &str, selected: bool, line: &Text, column: usize, offset: usize| {
+        let sep = colors.separator.paint(if selected { ":" } else { "-" });
+        let mut prefix = String::new();
+        if with_filename {
+            let _ = write!(prefix, "{}{sep}", colors.filename.paint(fname));
+        }
+        if line_number {
+            let _ = write!(prefix, "{}{sep}", colors.line_number.paint(line.number.to_string()));
+        }
+        if args.column {
+            let _ = write!(prefix, "{}{sep}", colors.line_number.paint(column.to_string()));
+        }
+        if args.byte_offset {
+            let _ = write!(prefix, "{}{sep}", colors.byte_offset.paint(offset.to_string()));
+        }
+        prefix
+    };
+    let print = |out: &mut StdoutLock, fname: &str, selected: bool, line: &Text| {
+        if args.only_matching {
+            // Lines of context have nothing to show
+            if selected {
+                for span in line.spans.iter().filter(|span| !span.is_empty()) {
+                    let prefix =
+                        prefix(fname, selected, line, span.start + 1, line.offset + span.start);
+                    let text = &line.text[span.clone()];
+                    writeln!(out, "{prefix}{}", colors.selected_match.paint(text))?;
+                }
+            }
+            Ok(())
         } else {
-            print!("{val}")
+            let column = line.spans.first().map_or(1, |span| span.start + 1);
+            let prefix = prefix(fname, selected, line, column, line.offset);
+            write!(out, "{prefix}{}", colors.paint_line(&line.text, &line.spans, selected))
         }
     };
+    let group_separator = colors.separator.paint("--");
+    // Whether a file has a match is known at its first one
+    let names_only = args.quiet || args.files_with_matches || args.files_without_match;
+    let max_count = if names_only { Some(1) } else { args.max_count };
+    let mut out = io::stdout().lock();
+    let mut printed = false;
+    let mut matched = false;
     for entry in entries {
         match entry {
             Err(e) => eprintln!("{e}"),
             Ok(filename) => match open(&filename) {
                 Err(e) => eprintln!("{filename}: {e}"),
-                Ok(file) => match find_lines(file, &pattern, args.invert) {
-                    Err(e) => eprintln!("{e}"),
-                    Ok(matches) => {
-                        if args.count {
-                            print(&filename, &format!("{}\n", matches.len()));
-                        } else {
-                            for line in matches {
-                                print(&filename, &line);
+                Ok(file) => {
+                    let fname = display_name(&filename, &args.label);
+                    let result = if names_only || args.count {
+                        find_lines(file, &matcher, args.invert, Context::default(), max_count, |_| Ok(()))
+                    } else {
+                        let mut first = true;
+                        find_lines(file, &matcher, args.invert, context, max_count, |line| {
+                            // Groups in different files are never next to
+                            // each other
+                            if first && printed && !context.is_empty() {
+                                writeln!(out, "{group_separator}")?;
+                            }
+                            first = false;
+                            printed = true;
+                            match line {
+                                Line::Match(text) => print(&mut out, fname, true, &text),
+                                Line::Context(text) => print(&mut out, fname, false, &text),
+                                Line::Break => writeln!(out, "{group_separator}"),
+                            }
+                        })
+                    };
+                    match result {
+                        // Nothing more can be printed
+                        Err(e) if e.is::<WriteError>() => return Err(e),
+                        Err(e) => eprintln!("{e}"),
+                        Ok(count) => {
+                            matched |= count > 0;
+                            if args.quiet {
+                                if matched {
+                                    break;
+                                }
+                            } else if args.files_with_matches || args.files_without_match {
+                                if (count > 0) == args.files_with_matches {
+                                    writeln!(out, "{}", colors.filename.paint(fname))
+                                        .map_err(WriteError)?;
+                                }
+                            } else if args.count {
+                                if with_filename {
+                                    write!(
+                                        out,
+                                        "{}{}",
+                                        colors.filename.paint(fname),
+                                        colors.separator.paint(":")
+                                    )
+                                    .map_err(WriteError)?;
+                                }
+                                writeln!(out, "{count}").map_err(WriteError)?;
                             }
                         }
                     }
@@ -77,7 +372,35 @@ fn run(args: Args) -> Result<()> {
         }
     }
 
-    Ok(())
+    out.flush().map_err(WriteError)?;
+    Ok(matched)
+}
+
+/// Output that could not be written. Unlike a file that cannot be read,
+/// this ends the search.
+#[derive(Debug)]
+struct WriteError(io::Error);
+
+impl WriteError {
+    fn broken_pipe(&self) -> bool {
+        self.0.kind() == io::ErrorKind::BrokenPipe
+    }
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Error for WriteError {}
+
+/// The name to print for a file, `label` for standard input
+fn display_name<'a>(filename: &'a str, label: &'a str) -> &'a str {
+    match filename {
+        "-" => label,
+        _ => filename,
+    }
 }
 
 fn open(filename: &str) -> Result<Box<dyn BufRead>> {
@@ -87,21 +410,52 @@ fn open(filename: &str) -> Result<Box<dyn BufRead>> {
     }
 }
 
+/// Hand each line to print to `output` as soon as it is known, stopping after
+/// `max_count` matches and the context that follows the last of them, or at
+/// the first error from `output`. Returns the number of matches.
 fn find_lines<T: BufRead>(
     mut file: T,
-    pattern: &Regex,
-    invert: bool
-) -> Result<Vec<String>>{ // Equivalent to: file impl BufRead
-    let mut matches: Vec<String> = vec![];
+    matcher: &Matcher,
+    invert: bool,
+    context: Context,
+    max_count: Option<usize>,
+    mut output: impl FnMut(Line) -> io::Result<()>,
+) -> Result<usize>{ // Equivalent to: file impl BufRead
+    let mut output = |line| output(line).map_err(WriteError);
+    let mut count = 0;
     let mut line = String::new();
+    // The last few lines that were not printed, in case a match follows
+    let mut before: VecDeque<Text> = VecDeque::with_capacity(context.before);
+    // Lines still to print after the last match
+    let mut after = 0;
+    // The number of the last line printed, to know when to print a break
+    let mut last_printed: Option<usize> = None;
+    // Where the next line starts
+    let mut offset = 0;
+
+    for line_num in 0.. {
+        let done = max_count.is_some_and(|max| count >= max);
+        if done && after == 0 {
+            break;
+        }
 
-    loop {
         let bytes = file.read_line(&mut line)?;
 
         if bytes == 0 {
             break;
         }
 
+        let content = line.strip_suffix('\n').unwrap_or(&line);
+        let spans = matcher.find_iter(content);
+        let is_match = !spans.is_empty();
+        let text = Text {
+            number: line_num + 1,
+            offset,
+            text: mem::take(&mut line), // use std::mem::take to take ownership of the line. Could have used clone to copy the string and add it to the matches, but take avoids an unnecessary copy
+            spans,
+        };
+        offset += bytes;
+
         // if invert {
         //     if !pattern.is_match(&line) {
         //         matches.push(line.clone());
@@ -116,17 +470,38 @@ fn find_lines<T: BufRead>(
         //     matches.push(line.clone());
         // }
 
-        if pattern.is_match(&line) ^ invert { // ^BitXor bit-wise exclusive OR
-            matches.push(mem::take(&mut line)); // use std::mem::take to take ownership of the line. Could have used clone to copy the string and add it to the matches, but take avoids an unnecessary copy
+        // Past -m, even lines that match are only context for the last one
+        if is_match ^ invert && !done { // ^BitXor bit-wise exclusive OR
+            let first = line_num - before.len();
+            if !context.is_empty() && last_printed.is_some_and(|last| first > last + 1) {
+                output(Line::Break)?;
+            }
+            before.drain(..).try_for_each(|text| output(Line::Context(text)))?;
+            output(Line::Match(text))?;
+            count += 1;
+            last_printed = Some(line_num);
+            after = context.after;
+        } else if after > 0 {
+            output(Line::Context(text))?;
+            last_printed = Some(line_num);
+            after -= 1;
+        } else if context.before > 0 {
+            if before.len() == context.before {
+                before.pop_front();
+            }
+            before.push_back(text);
         }
-
-        line.clear();
     }
 
-    Ok(matches)
+    Ok(count)
 }
 
-fn find_files(paths: &[String], recursive: bool) -> Vec<Result<String>> {
+fn find_files(
+    paths: &[String],
+    recursive: bool,
+    respect_ignore: bool,
+    hidden: bool,
+) -> Vec<Result<String>> {
     let mut results = vec![];
 
     for path in paths {
@@ -136,10 +511,12 @@ fn find_files(paths: &[String], recursive: bool) -> Vec<Result<String>> {
                 Ok(metadata) => {
                     if metadata.is_dir() {
                         if recursive {
-                            for entry in WalkDir::new(path)
-                                .into_iter()
+                            for entry in WalkBuilder::new(path)
+                                .standard_filters(respect_ignore)
+                                .hidden(respect_ignore && !hidden)
+                                .build()
                                 .flatten() // Will ignore Err and None variants on Result and Option
-                                .filter(|e| e.file_type().is_file()) 
+                                .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
                                 {
                                     results
                                         .push(Ok(entry
@@ -169,18 +546,42 @@ fn _get_args() -> Args {
         .version("0.1.0")
         .author("Ken Youens-Clark <kyclark@gmail.com>")
         .about("Rust version of `grep`")
+        .disable_help_flag(true)
         .arg(
             Arg::new("pattern")
                 .value_name("PATTERN")
-                .help("Search pattern")
-                .required(true),
+                .help("Search pattern, unless -e or -f give the patterns")
+                .required_unless_present_any(["regexp", "file"]),
         )
         .arg(
             Arg::new("files")
                 .value_name("FILE")
-                .help("Input file(s)")
-                .num_args(1..)
-                .default_value("-"),
+                .help("Input file(s) [default: -]")
+                .num_args(1..),
+        )
+        .arg(
+            Arg::new("regexp")
+                .short('e')
+                .long("regexp")
+                .value_name("PATTERN")
+                .help("Search for PATTERN, given more than once to search for any of them")
+                .allow_hyphen_values(true)
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("file")
+                .short('f')
+                .long("file")
+                .value_name("FILE")
+                .help("Read patterns from FILE, one per line")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("fixed_strings")
+                .short('F')
+                .long("fixed-strings")
+                .help("Take the patterns as strings to find as they are")
+                .action(ArgAction::SetTrue),
         )
         .arg(
             Arg::new("insensitive")
@@ -196,6 +597,18 @@ fn _get_args() -> Args {
                 .help("Recursive search")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("respect_ignore")
+                .long("respect-ignore")
+                .help("Skip ignored and hidden files when recursing")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("hidden")
+                .long("hidden")
+                .help("Search hidden files with --respect-ignore")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("count")
                 .short('c')
@@ -210,44 +623,212 @@ fn _get_args() -> Args {
                 .help("Invert match")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("only_matching")
+                .short('o')
+                .long("only-matching")
+                .help("Print only the matches, each on its own line")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("word_regexp")
+                .short('w')
+                .long("word-regexp")
+                .help("Match only whole words")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("line_regexp")
+                .short('x')
+                .long("line-regexp")
+                .help("Match only whole lines")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("max_count")
+                .short('m')
+                .long("max-count")
+                .value_name("NUM")
+                .help("Stop reading a file after NUM matching lines")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .alias("silent")
+                .help("Print nothing and stop at the first match")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("files_with_matches")
+                .short('l')
+                .long("files-with-matches")
+                .help("Print only the names of files with a match")
+                .conflicts_with("files_without_match")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("files_without_match")
+                .short('L')
+                .long("files-without-match")
+                .help("Print only the names of files without a match")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("after_context")
+                .short('A')
+                .long("after-context")
+                .value_name("NUM")
+                .help("Print NUM lines after each match")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("before_context")
+                .short('B')
+                .long("before-context")
+                .value_name("NUM")
+                .help("Print NUM lines before each match")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("context")
+                .short('C')
+                .long("context")
+                .value_name("NUM")
+                .help("Print NUM lines before and after each match")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("line_number")
+                .short('n')
+                .long("line-number")
+                .help("Print the line number before each line")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("byte_offset")
+                .short('b')
+                .long("byte-offset")
+                .help("Print the byte offset of each line in its file")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("column")
+                .long("column")
+                .help("Print the column of the first match on each line")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("with_filename")
+                .short('H')
+                .long("with-filename")
+                .help("Print the file name before each line")
+                .overrides_with("no_filename")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no_filename")
+                .short('h')
+                .long("no-filename")
+                .help("Never print file names")
+                .overrides_with("with_filename")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("label")
+                .long("label")
+                .value_name("LABEL")
+                .help("Name to print for standard input")
+                .default_value("(standard input)"),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .alias("colour")
+                .value_name("WHEN")
+                .help("Highlight matches, file names, line numbers and separators")
+                .value_parser(clap::value_parser!(ColorChoice))
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_value("auto")
+                .default_missing_value("auto"),
+        )
+        .arg(
+            Arg::new("help")
+                .long("help")
+                .help("Print help")
+                .action(ArgAction::Help),
+        )
         .get_matches();
     Args {
-        pattern: matches.get_one("pattern").cloned().unwrap(),
-        files: matches.get_many("files").unwrap().cloned().collect(),
+        pattern: matches.get_one("pattern").cloned(),
+        files: matches.get_many("files").unwrap_or_default().cloned().collect(),
+        regexp: matches.get_many("regexp").unwrap_or_default().cloned().collect(),
+        file: matches.get_many("file").unwrap_or_default().cloned().collect(),
+        fixed_strings: matches.get_flag("fixed_strings"),
         insensitive: matches.get_flag("insensitive"),
         recursive: matches.get_flag("recursive"),
+        respect_ignore: matches.get_flag("respect_ignore"),
+        hidden: matches.get_flag("hidden"),
         count: matches.get_flag("count"),
         invert: matches.get_flag("invert"),
+        only_matching: matches.get_flag("only_matching"),
+        word_regexp: matches.get_flag("word_regexp"),
+        line_regexp: matches.get_flag("line_regexp"),
+        max_count: matches.get_one("max_count").copied(),
+        quiet: matches.get_flag("quiet"),
+        files_with_matches: matches.get_flag("files_with_matches"),
+        files_without_match: matches.get_flag("files_without_match"),
+        after_context: matches.get_one("after_context").copied(),
+        before_context: matches.get_one("before_context").copied(),
+        context: matches.get_one("context").copied(),
+        line_number: matches.get_flag("line_number"),
+        byte_offset: matches.get_flag("byte_offset"),
+        column: matches.get_flag("column"),
+        with_filename: matches.get_flag("with_filename"),
+        no_filename: matches.get_flag("no_filename"),
+        label: matches.get_one("label").cloned().unwrap(),
+        color: matches.get_one("color").copied().unwrap(),
+        help: None,
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
 mod tests {
-    use std::io::Cursor;
+    use std::{io::Cursor, ops::Range};
 
     use rand::{distributions::Alphanumeric, Rng};
     use regex::{Regex, RegexBuilder};
 
-    use crate::find_lines;
+    use ansi_term::{Colour, Style};
+
+    use crate::{
+        colors::Colors,
+        find_lines,
+        matcher::{MatchOptions, Matcher},
+        Context, Line, Text,
+    };
 
     use super::find_files;
 
     #[test]
     fn test_find_files() {
         // Verify that the function finds a file known to exist
-        let files = find_files(&["./tests/inputs/fox.txt".to_string()], false);
+        let files = find_files(&["./tests/inputs/fox.txt".to_string()], false, false, false);
         assert_eq!(files.len(), 1);
         assert_eq!(files[0].as_ref().unwrap(), "./tests/inputs/fox.txt");
 
         // The function should reject a directory without the recursive option
-        let files = find_files(&["./tests/inputs".to_string()], false);
+        let files = find_files(&["./tests/inputs".to_string()], false, false, false);
         assert_eq!(files.len(), 1);
         if let Err(e) = &files[0] {
             assert_eq!(e.to_string(), "./tests/inputs is a directory");
         }
 
         // Verify the function recurses to find four files in the directory
-        let res = find_files(&["./tests/inputs".to_string()], true);
+        let res = find_files(&["./tests/inputs".to_string()], true, false, false);
         let mut files: Vec<String> = res
             .iter()
             .map(|r| r.as_ref().unwrap().replace("\\", "/"))
@@ -272,43 +853,287 @@ mod tests {
             .collect();
 
         // Verify that the function returns the bad file as an error
-        let files = find_files(&[bad], false);
+        let files = find_files(&[bad], false, false, false);
         assert_eq!(files.len(), 1);
         assert!(files[0].is_err());
     }
 
+    /// Run `find_lines`, keeping the lines it would print
+    fn collect(
+        text: &[u8],
+        matcher: &Matcher,
+        invert: bool,
+        context: Context,
+        max_count: Option<usize>,
+    ) -> Vec<Line> {
+        let mut lines = vec![];
+        find_lines(Cursor::new(text), matcher, invert, context, max_count, |line| {
+            lines.push(line);
+            Ok(())
+        })
+        .unwrap();
+        lines
+    }
+
     #[test]
     fn test_find_lines() {
         let text = b"Lorem\nIpsum\nDOLOR";
 
         // The pattern _or_ should match the one line, "Lorem"
-        let re1 = Regex::new("or").unwrap();
-        let matches = find_lines(Cursor::new(&text), &re1, false);
+        let re1 = Matcher::Regex(Regex::new("or").unwrap());
+        let matches = find_lines(Cursor::new(&text), &re1, false, Context::default(), None, |_| Ok(()));
         assert!(matches.is_ok());
-        assert_eq!(matches.unwrap().len(), 1);
+        assert_eq!(matches.unwrap(), 1);
 
         // When inverted, the function should match the other two lines
-        let matches = find_lines(Cursor::new(&text), &re1, true);
+        let matches = find_lines(Cursor::new(&text), &re1, true, Context::default(), None, |_| Ok(()));
         assert!(matches.is_ok());
-        assert_eq!(matches.unwrap().len(), 2);
+        assert_eq!(matches.unwrap(), 2);
 
         // This regex will be case-insensitive
-        let re2 = RegexBuilder::new("or")
-            .case_insensitive(true)
-            .build()
-            .unwrap();
+        let re2 = Matcher::Regex(
+            RegexBuilder::new("or")
+                .case_insensitive(true)
+                .build()
+                .unwrap(),
+        );
 
         // The two lines "Lorem" and "DOLOR" should match
-        let matches = find_lines(Cursor::new(&text), &re2, false);
+        let matches = find_lines(Cursor::new(&text), &re2, false, Context::default(), None, |_| Ok(()));
         assert!(matches.is_ok());
-        assert_eq!(matches.unwrap().len(), 2);
+        assert_eq!(matches.unwrap(), 2);
 
         // When inverted, the one remaining line should match
-        let matches = find_lines(Cursor::new(&text), &re2, true);
+        let matches = find_lines(Cursor::new(&text), &re2, true, Context::default(), None, |_| Ok(()));
         assert!(matches.is_ok());
-        assert_eq!(matches.unwrap().len(), 1);
+        assert_eq!(matches.unwrap(), 1);
+    }
+
+    #[test]
+    fn test_find_lines_spans() {
+        let text = b"one two three\nfour\n";
+        let re = Matcher::Regex(Regex::new("o").unwrap());
+        let lines = collect(text, &re, false, Context::default(), None);
+        assert_eq!(lines.len(), 2);
+        if let Line::Match(line) = &lines[0] {
+            assert_eq!(line.spans, vec![0..1, 6..7]);
+        }
+        if let Line::Match(line) = &lines[1] {
+            assert_eq!(line.spans, vec![1..2]);
+            assert_eq!(line.offset, 14);
+        }
+
+        // The line ending is not part of the line, so "$" matches before it
+        let re = Matcher::Regex(Regex::new("r$").unwrap());
+        let lines = collect(text, &re, false, Context::default(), None);
+        assert_eq!(lines.len(), 1);
+
+        // A line that does not match has no spans to show
+        let re = Matcher::Regex(Regex::new("two").unwrap());
+        let lines = collect(text, &re, true, Context::default(), None);
+        assert!(matches!(&lines[..], [Line::Match(line)] if line.spans.is_empty()));
+    }
+
+    #[test]
+    fn test_colors() {
+        let colors = Colors::parse("");
+        assert_eq!(colors, Colors::default());
+        assert_eq!(colors.selected_match, Colour::Red.bold());
+
+        let colors = Colors::parse("mt=01;32:fn=38;5;208:ln=38;2;1;2;3:se=:sl=7:rv:ne");
+        assert_eq!(colors.selected_match, Colour::Green.bold());
+        assert_eq!(colors.context_match, Colour::Green.bold());
+        assert_eq!(colors.filename, Colour::Fixed(208).normal());
+        assert_eq!(colors.line_number, Colour::RGB(1, 2, 3).normal());
+        assert_eq!(colors.separator, Style::new());
+        assert_eq!(colors.selected_line, Style::new().reverse());
+
+        // What cannot be read keeps the default
+        let colors = Colors::parse("ms=31;x:fn=38;5:ln=99:bn");
+        assert_eq!(colors, Colors::default());
+
+        let colors = Colors::parse("ms=1:sl=2");
+        assert_eq!(
+            colors.paint_line("a bc b\n", &[2..3, 3..4], true),
+            "\x1b[2ma \x1b[0m\x1b[1mb\x1b[0m\x1b[1mc\x1b[0m\x1b[2m b\x1b[0m\n"
+        );
+        assert_eq!(colors.paint_line("a b", &[2..3], false), "a \x1b[1;31mb\x1b[0m");
+        assert_eq!(Colors::plain().paint_line("a b\n", &[2..3], true), "a b\n");
+    }
+
+    #[test]
+    fn test_matcher() {
+        let patterns = |patterns: &[&str]| -> Vec<String> {
+            patterns.iter().map(|p| p.to_string()).collect()
+        };
+        let fixed = MatchOptions {
+            fixed_strings: true,
+            ..Default::default()
+        };
+
+        // The longest of the strings that start at the same place wins
+        let matcher = Matcher::new(&patterns(&["a", "an", "and", "."]), fixed).unwrap();
+        assert!(matches!(matcher, Matcher::Fixed(_)));
+        assert_eq!(matcher.find_iter("band a.b"), vec![1..4, 5..6, 6..7]);
+
+        let options = MatchOptions {
+            insensitive: true,
+            ..fixed
+        };
+        let matcher = Matcher::new(&patterns(&["AND"]), options).unwrap();
+        assert!(matches!(matcher, Matcher::Fixed(_)));
+        assert_eq!(matcher.find_iter("Band"), vec![1..4]);
+
+        // Only the regex engine folds the case of more than ASCII
+        let matcher = Matcher::new(&patterns(&["ÉMILE", "a+"]), options).unwrap();
+        assert!(matches!(matcher, Matcher::Regex(_)));
+        assert_eq!(matcher.find_iter("émile a+ aa"), vec![0..6, 7..9]);
+
+        let options = MatchOptions { word: true, ..fixed };
+        let matcher = Matcher::new(&patterns(&["an"]), options).unwrap();
+        assert_eq!(matcher.find_iter("band an"), vec![5..7]);
+        // Words next to each other share the character between them
+        assert_eq!(matcher.find_iter("an an"), vec![0..2, 3..5]);
+        // A word may start or end with punctuation, only what is around it counts
+        let matcher = Matcher::new(&patterns(&["@foo", "bar!"]), options).unwrap();
+        assert_eq!(matcher.find_iter("x @foo bar! a@foo"), vec![2..6, 7..11]);
+
+        let options = MatchOptions {
+            word: true,
+            ..Default::default()
+        };
+        let matcher = Matcher::new(&patterns(&["fo+"]), options).unwrap();
+        assert_eq!(matcher.find_iter("foofoo foo"), vec![7..10]);
+
+        // Regular expressions are tried together, -x around all of them
+        let options = MatchOptions {
+            line: true,
+            ..Default::default()
+        };
+        let matcher = Matcher::new(&patterns(&["a+", "b"]), options).unwrap();
+        assert_eq!(matcher.find_iter("aaa"), vec![0..3]);
+        assert_eq!(matcher.find_iter("b"), vec![0..1]);
+        assert!(matcher.find_iter("ab").is_empty());
+
+        let err = Matcher::new(&patterns(&["a", "*b"]), MatchOptions::default()).unwrap_err();
+        assert_eq!(err.to_string(), r#"Invalid pattern "*b""#);
+
+        // No patterns match nothing, not even an empty line
+        let matcher = Matcher::new(&[], MatchOptions::default()).unwrap();
+        assert!(matcher.find_iter("").is_empty());
+    }
+
+    #[test]
+    fn test_find_lines_context() {
+        let text = b"a\nb\nc\nd\ne\nf\ng\nh\n";
+        // Each line is two bytes long
+        let numbered = |text: &str, spans: Vec<Range<usize>>| {
+            let number = (text.as_bytes()[0] - b'a') as usize + 1;
+            Text {
+                number,
+                offset: (number - 1) * 2,
+                text: format!("{text}\n"),
+                spans,
+            }
+        };
+        let line = |text: &str| Line::Match(numbered(text, vec![0..1]));
+        let context = |text: &str| Line::Context(numbered(text, vec![]));
+
+        // One line on either side of "b" and "g", with a break between them
+        let re = Matcher::Regex(Regex::new("[bg]").unwrap());
+        let lines = collect(
+            text,
+            &re,
+            false,
+            Context {
+                before: 1,
+                after: 1,
+            },
+            None,
+        );
+        assert_eq!(
+            lines,
+            vec![
+                context("a"),
+                line("b"),
+                context("c"),
+                Line::Break,
+                context("f"),
+                line("g"),
+                context("h"),
+            ]
+        );
+
+        // Groups that touch are joined without a break
+        let lines = collect(
+            text,
+            &re,
+            false,
+            Context {
+                before: 2,
+                after: 2,
+            },
+            None,
+        );
+        assert_eq!(
+            lines,
+            vec![
+                context("a"),
+                line("b"),
+                context("c"),
+                context("d"),
+                context("e"),
+                context("f"),
+                line("g"),
+                context("h"),
+            ]
+        );
+
+        // Lines of context are not repeated when matches are close together
+        let re = Matcher::Regex(Regex::new("[cd]").unwrap());
+        let lines = collect(
+            text,
+            &re,
+            false,
+            Context {
+                before: 3,
+                after: 0,
+            },
+            None,
+        );
+        assert_eq!(
+            lines,
+            vec![context("a"), context("b"), line("c"), line("d")]
+        );
+    }
+
+    #[test]
+    fn test_find_lines_max_count() {
+        let text = b"a\nb\na\nb\na\n";
+        let re = Matcher::Regex(Regex::new("a").unwrap());
+
+        let lines = collect(text, &re, false, Context::default(), Some(2));
+        assert_eq!(lines.len(), 2);
+        assert!(collect(text, &re, false, Context::default(), Some(0)).is_empty());
 
+        // The context after the last match is printed, matches or not
+        let context = Context {
+            before: 0,
+            after: 2,
+        };
+        let lines = collect(text, &re, false, context, Some(1));
+        assert!(matches!(
+            &lines[..],
+            [Line::Match(_), Line::Context(_), Line::Context(a)] if a.number == 3
+        ));
 
+        // Nothing past the last match is read, not even a line that is not
+        // UTF-8
+        let text = b"a\na\n\xff\n";
+        let count = find_lines(Cursor::new(&text), &re, false, Context::default(), Some(2), |_| Ok(()));
+        assert_eq!(count.unwrap(), 2);
+        let count = find_lines(Cursor::new(&text), &re, false, Context::default(), None, |_| Ok(()));
+        assert!(count.is_err());
     }
 }
 
@@ -0,0 +1,104 @@
+use std::ops::Range;
+
+use aho_corasick::{AhoCorasick, MatchKind};
+use anyhow::{anyhow, Result};
+use regex::{Regex, RegexBuilder};
+
+/// How the patterns are read and what they have to match
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MatchOptions {
+    /// -F: the patterns are strings to find as they are
+    pub fixed_strings: bool,
+    /// -i
+    pub insensitive: bool,
+    /// -w: matches are whole words
+    pub word: bool,
+    /// -x: matches are whole lines, which wins over `word` as in grep
+    pub line: bool,
+}
+
+/// Finds any of the patterns in a line
+#[derive(Debug)]
+pub enum Matcher {
+    Regex(Regex),
+    /// -w: the patterns in group 1, between characters that are not word
+    /// characters or the ends of the line
+    Word(Regex),
+    /// All of the -F strings, looked for in one pass over the line
+    Fixed(AhoCorasick),
+}
+
+impl Matcher {
+    pub fn new(patterns: &[String], options: MatchOptions) -> Result<Self> {
+        // Aho-Corasick only folds the case of ASCII and knows nothing of words
+        // or lines, so other fixed strings are escaped for the regex engine
+        if options.fixed_strings
+            && !options.word
+            && !options.line
+            && (!options.insensitive || patterns.iter().all(|p| p.is_ascii()))
+        {
+            let fixed = AhoCorasick::builder()
+                .match_kind(MatchKind::LeftmostLongest)
+                .ascii_case_insensitive(options.insensitive)
+                .build(patterns)?;
+            return Ok(Matcher::Fixed(fixed));
+        }
+        // Nothing matches no patterns at all, as from an empty -f file
+        if patterns.is_empty() {
+            return Ok(Matcher::Fixed(AhoCorasick::new(patterns)?));
+        }
+
+        let alternatives: Vec<String> = patterns
+            .iter()
+            .map(|pattern| match options.fixed_strings {
+                true => format!("(?:{})", regex::escape(pattern)),
+                false => format!("(?:{pattern})"),
+            })
+            .collect();
+        let alternatives = alternatives.join("|");
+        // Like grep, -w looks at the characters around a match rather than for
+        // `\b`, so that patterns may start or end with punctuation
+        let (regex, make): (_, fn(Regex) -> Matcher) = if options.line {
+            (format!("^(?:{alternatives})$"), Matcher::Regex)
+        } else if options.word {
+            (format!(r"(?:^|\W)({alternatives})(?:\W|$)"), Matcher::Word)
+        } else {
+            (alternatives, Matcher::Regex)
+        };
+        RegexBuilder::new(&regex)
+            .case_insensitive(options.insensitive)
+            .build()
+            .map(make)
+            .map_err(|e| {
+                // Blame the first pattern that is wrong on its own, if any
+                match patterns.iter().find(|pattern| Regex::new(pattern).is_err()) {
+                    Some(pattern) => anyhow!(r#"Invalid pattern "{pattern}""#),
+                    None => anyhow!(e),
+                }
+            })
+    }
+
+    /// The byte ranges of `text` that match, left to right and not
+    /// overlapping
+    pub fn find_iter(&self, text: &str) -> Vec<Range<usize>> {
+        match self {
+            Matcher::Regex(regex) => regex.find_iter(text).map(|m| m.range()).collect(),
+            Matcher::Word(regex) => {
+                let mut spans = vec![];
+                let mut start = 0;
+                while let Some(word) = regex.captures_at(text, start).and_then(|c| c.get(1)) {
+                    spans.push(word.range());
+                    // The character after a word may also be the one before the next,
+                    // so the search goes on from the end of the word itself
+                    start = match text[word.end()..].chars().next() {
+                        Some(c) if word.is_empty() => word.end() + c.len_utf8(),
+                        None if word.is_empty() => break,
+                        _ => word.end(),
+                    };
+                }
+                spans
+            }
+            Matcher::Fixed(fixed) => fixed.find_iter(text).map(|m| m.range()).collect(),
+        }
+    }
+}
@@ -285,3 +285,503 @@ fn stdin_insensitive_count() -> Result<()> {
     assert_eq!(stdout, expected);
     Ok(())
 }
+
+// --------------------------------------------------
+#[test]
+fn recursive_respect_ignore() -> Result<()> {
+    let dir = tempfile::Builder::new().prefix("grepr-").tempdir()?;
+    let dir = dir.path();
+    fs::create_dir_all(dir.join("repo/.git"))?;
+    fs::create_dir_all(dir.join("repo/target"))?;
+    fs::create_dir_all(dir.join("home"))?;
+    let repo = dir.join("repo");
+    fs::write(repo.join(".gitignore"), "target/\n*.log\n!keep.log\n")?;
+    let files = ["fox.txt", ".fox.txt", "target/fox.txt", "a.log", "keep.log"];
+    for file in files {
+        fs::write(repo.join(file), "The quick brown fox\n")?;
+    }
+
+    let root = repo.to_string_lossy().to_string();
+    let search = |args: &[&str]| -> Result<Vec<String>> {
+        let output = Command::cargo_bin(PRG)?
+            .env("HOME", dir.join("home"))
+            .env_remove("XDG_CONFIG_HOME")
+            .args(args)
+            .args(["fox", &root])
+            .output()?;
+        assert!(output.status.success());
+        let mut files: Vec<String> = String::from_utf8(output.stdout)?
+            .lines()
+            .map(|line| {
+                let file = line.split(':').next().unwrap_or_default();
+                file.replacen(&root, "", 1).replace('\\', "/")
+            })
+            .collect();
+        files.sort();
+        Ok(files)
+    };
+
+    assert_eq!(
+        search(&["-r"])?,
+        [
+            "/.fox.txt",
+            "/a.log",
+            "/fox.txt",
+            "/keep.log",
+            "/target/fox.txt"
+        ]
+    );
+    assert_eq!(
+        search(&["-r", "--respect-ignore"])?,
+        ["/fox.txt", "/keep.log"]
+    );
+    assert_eq!(
+        search(&["-r", "--respect-ignore", "--hidden"])?,
+        ["/.fox.txt", "/fox.txt", "/keep.log"]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_context() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-C", "x", "foo", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value 'x'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn nobody_after_context() -> Result<()> {
+    run(
+        &["-A", "1", "Nobody", NOBODY],
+        "tests/expected/nobody.txt.Nobody.after",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn nobody_before_context() -> Result<()> {
+    run(
+        &["--before-context", "2", "tell", NOBODY],
+        "tests/expected/nobody.txt.tell.before",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_context() -> Result<()> {
+    run(
+        &["-C", "1", "the", BUSTLE],
+        "tests/expected/bustle.txt.the.context",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn multiple_files_context() -> Result<()> {
+    run(
+        &["--context", "1", "-i", "the", BUSTLE, EMPTY, FOX, NOBODY],
+        "tests/expected/all.the.insensitive.context",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_numbered() -> Result<()> {
+    run(
+        &["-n", "--byte-offset", "-C", "1", "the", BUSTLE],
+        "tests/expected/bustle.txt.the.numbered",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_column() -> Result<()> {
+    run(
+        &["--column", "-H", "the", BUSTLE],
+        "tests/expected/bustle.txt.the.column",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn nobody_with_filename() -> Result<()> {
+    run(
+        &["-H", "-b", "Nobody", NOBODY],
+        "tests/expected/nobody.txt.Nobody.with_filename",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn multiple_files_no_filename() -> Result<()> {
+    run(
+        &["-H", "--no-filename", "-i", "the", BUSTLE, FOX],
+        "tests/expected/the.insensitive.no_filename",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn stdin_label() -> Result<()> {
+    let input = fs::read_to_string(FOX)?;
+    let expected = fs::read_to_string("tests/expected/fox.txt.label")?;
+
+    let output = Command::cargo_bin(PRG)?
+        .args(["-Hn", "--label", "fox.txt", "fox"])
+        .write_stdin(input)
+        .output()
+        .expect("fail");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("invalid UTF-8");
+    assert_eq!(stdout, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn multiple_files_only_matching() -> Result<()> {
+    run(
+        &["-o", "-b", "-i", "the", BUSTLE, FOX],
+        "tests/expected/the.insensitive.only_matching",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_word() -> Result<()> {
+    run(
+        &["--word-regexp", "-i", "the", BUSTLE],
+        "tests/expected/bustle.txt.the.word",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn word_with_punctuation() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-w", "@foo"])
+        .write_stdin("x @foo y\nx@foo y\n")
+        .assert()
+        .success()
+        .stdout("x @foo y\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn nobody_line() -> Result<()> {
+    run(
+        &["-x", "To an admiring Bog!", NOBODY],
+        "tests/expected/nobody.txt.line",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn nobody_line_partial() -> Result<()> {
+    run(
+        &["--line-regexp", "To an", NOBODY],
+        "tests/expected/empty.foo",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn nobody_word_only_matching() -> Result<()> {
+    run(
+        &["-onwi", "you", NOBODY],
+        "tests/expected/nobody.txt.you.word.only_matching",
+    )
+}
+
+// --------------------------------------------------
+fn run_with_env(
+    args: &[&str],
+    env: &[(&str, &str)],
+    expected_file: &str,
+) -> Result<()> {
+    let expected = fs::read_to_string(expected_file)?;
+    let output = Command::cargo_bin(PRG)?
+        .env_remove("GREP_COLORS")
+        .env_remove("NO_COLOR")
+        .envs(env.iter().copied())
+        .args(args)
+        .output()
+        .expect("fail");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("invalid UTF-8");
+    assert_eq!(stdout, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn color_always() -> Result<()> {
+    run_with_env(
+        &["--color=always", "-n", "-C", "1", "the", BUSTLE, FOX],
+        &[],
+        "tests/expected/the.context.color",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn color_always_no_color() -> Result<()> {
+    // NO_COLOR only changes what auto does
+    run_with_env(
+        &["--colour=always", "-n", "-C", "1", "the", BUSTLE, FOX],
+        &[("NO_COLOR", "1")],
+        "tests/expected/the.context.color",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn color_auto_not_terminal() -> Result<()> {
+    run_with_env(
+        &["--color", "-C", "1", "the", BUSTLE],
+        &[],
+        "tests/expected/bustle.txt.the.context",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn color_never() -> Result<()> {
+    run_with_env(
+        &["--color=never", "-C", "1", "the", BUSTLE],
+        &[],
+        "tests/expected/bustle.txt.the.context",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn color_grep_colors() -> Result<()> {
+    run_with_env(
+        &["--color=always", "-nH", "Nobody", NOBODY],
+        &[("GREP_COLORS", "ms=04;32:ln=1:fn=38;5;208:se=")],
+        "tests/expected/nobody.txt.Nobody.grep_colors",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn color_only_matching() -> Result<()> {
+    run_with_env(
+        &["--color=always", "-o", "-i", "you", NOBODY],
+        &[],
+        "tests/expected/nobody.txt.you.only_matching.color",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_color() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--color=sometimes", "foo", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value 'sometimes'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_pattern_regexp() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-e", "fox", "-e", "*foo", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(r#"Invalid pattern "*foo""#));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_pattern_file() -> Result<()> {
+    let bad = gen_bad_file();
+    let expected = format!("{bad}: .* [(]os error 2[)]");
+    Command::cargo_bin(PRG)?
+        .args(["-f", &bad, FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::is_match(expected)?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn multiple_patterns() -> Result<()> {
+    run(
+        &["-e", "the", "--regexp", "How", BUSTLE, NOBODY],
+        "tests/expected/the.How.regexp",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn patterns_from_file() -> Result<()> {
+    run(
+        &["-f", "tests/patterns.txt", NOBODY],
+        "tests/expected/nobody.txt.patterns",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn empty_patterns_file() -> Result<()> {
+    run(&["--file", EMPTY, FOX], "tests/expected/empty.foo")
+}
+
+// --------------------------------------------------
+#[test]
+fn fixed_strings() -> Result<()> {
+    run(
+        &["-F", "-e", "you?", "-e", ".", NOBODY],
+        "tests/expected/nobody.txt.fixed",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn fixed_strings_only_matching() -> Result<()> {
+    run(
+        &["-F", "-e", "a", "-e", "an", "-oe", "and", BUSTLE],
+        "tests/expected/bustle.txt.fixed.only_matching",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn fixed_strings_insensitive_word() -> Result<()> {
+    run(
+        &["-Fiw", "-e", "nobody", "-e", "US", NOBODY],
+        "tests/expected/nobody.txt.fixed.insensitive.word",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn max_count() -> Result<()> {
+    run(
+        &["-m", "2", "the", BUSTLE, NOBODY],
+        "tests/expected/the.max_count",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn max_count_after_context() -> Result<()> {
+    run(
+        &["--max-count", "1", "-n", "-A", "2", "e", BUSTLE],
+        "tests/expected/bustle.txt.e.max_count.after",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn max_count_count() -> Result<()> {
+    run(
+        &["-c", "-m2", "e", BUSTLE, NOBODY],
+        "tests/expected/e.max_count.count",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn files_with_matches() -> Result<()> {
+    run(
+        &["-l", "the", BUSTLE, EMPTY, FOX, NOBODY],
+        "tests/expected/the.files_with_matches",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn files_without_match() -> Result<()> {
+    run(
+        &["--files-without-match", "the", BUSTLE, EMPTY, FOX, NOBODY],
+        "tests/expected/the.files_without_match",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_files_with_and_without_match() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-l", "-L", "the", BUSTLE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn quiet_match() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-q", "the", EMPTY, BUSTLE])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn quiet_no_match() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--quiet", "zebra", BUSTLE, FOX])
+        .assert()
+        .code(1)
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn quiet_stops_at_first_match() -> Result<()> {
+    // The bad file after the match is never opened
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["-q", "fox", FOX, &bad])
+        .assert()
+        .success()
+        .stderr("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn closed_stdout() -> Result<()> {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    // Far more output than a pipe holds, so grepr is still writing when
+    // the reader goes away
+    let dir = tempfile::Builder::new().prefix("grepr-").tempdir()?;
+    let big = dir.path().join("big.log");
+    fs::write(&big, "The quick brown fox\n".repeat(100_000))?;
+    let mut child =
+        std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+            .arg("fox")
+            .arg(&big)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+    let mut first = String::new();
+    BufReader::new(child.stdout.take().unwrap()).read_line(&mut first)?;
+    let output = child.wait_with_output()?;
+    assert_eq!(first, "The quick brown fox\n");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stderr)?, "");
+    Ok(())
+}
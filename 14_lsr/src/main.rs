@@ -9,6 +9,7 @@ use std::{fs, os::unix::fs::MetadataExt, path::PathBuf};
 use anyhow::Result;
 use chrono::{DateTime, Local};
 use clap::{Arg, ArgAction, Command, Parser};
+use entry_type::EntryType;
 use owner::Owner;
 use tabular::{Row, Table};
 use users::{get_group_by_gid, get_user_by_uid};
@@ -71,7 +72,9 @@ fn format_output(paths: &[PathBuf]) -> Result<String> {
     let mut table = Table::new(fmt);
 
     for path in paths {
-        let metadata = path.metadata()?;
+        // Like `ls -l` and `findr --type`, describe a link itself rather than
+        // what it points to
+        let metadata = fs::symlink_metadata(path)?;
 
         let uid = metadata.uid();
         let user = get_user_by_uid(uid)
@@ -83,13 +86,17 @@ fn format_output(paths: &[PathBuf]) -> Result<String> {
             .map(|g| g.name().to_string_lossy().into_owned())
             .unwrap_or_else(|| gid.to_string());
 
-        let file_type = if path.is_dir() { "d" } else { "-" };
+        let file_type = match EntryType::of(&metadata.file_type()) {
+            Some(EntryType::File) => '-',
+            Some(entry_type) => entry_type.letter(),
+            None => '?',
+        };
         let perms = format_mode(metadata.mode());
         let modified: DateTime<Local> = DateTime::from(metadata.modified()?);
 
         table.add_row(
             Row::new()
-                .with_cell(file_type) // 1 "d" or "-"
+                .with_cell(file_type) // 1 "d", "-", "l", "b", "c", "p" or "s"
                 .with_cell(perms) // 2 permissions
                 .with_cell(metadata.nlink()) // 3 number of links
                 .with_cell(user) // 4 user name
@@ -406,6 +413,21 @@ mod tests {
         long_match(&dir_line, "tests/inputs/dir", "drwxr-xr-x", None);
     }
 
+    #[test]
+    fn test_format_output_types() {
+        let link = std::env::temp_dir().join(format!("lsr-link-{}", std::process::id()));
+        std::os::unix::fs::symlink("tests/inputs/dir", &link).unwrap();
+        let res = format_output(&[PathBuf::from("/dev/null"), link.clone()]);
+        std::fs::remove_file(&link).unwrap();
+
+        let out = res.unwrap();
+        let types: Vec<char> = out
+            .lines()
+            .filter_map(|line| line.chars().next())
+            .collect();
+        assert_eq!(types, ['c', 'l']);
+    }
+
     #[test]
     fn test_mk_triple() {
         assert_eq!(mk_triple(0o751, Owner::User), "rwx");
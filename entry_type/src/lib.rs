@@ -0,0 +1,99 @@
+//! The kinds of entries on disk, shared by `findr` for `--type` and by `lsr`
+//! for its type column so that both classify entries the same way. The
+//! `clap` feature lets the letters be parsed as arguments.
+
+use std::fs::FileType;
+
+/// The kinds of entries `--type` tests for
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum EntryType {
+    Dir,
+    File,
+    Link,
+    BlockDevice,
+    CharDevice,
+    Fifo,
+    Socket,
+}
+
+impl EntryType {
+    pub fn of(file_type: &FileType) -> Option<EntryType> {
+        if file_type.is_dir() {
+            Some(EntryType::Dir)
+        } else if file_type.is_file() {
+            Some(EntryType::File)
+        } else if file_type.is_symlink() {
+            Some(EntryType::Link)
+        } else {
+            EntryType::special(file_type)
+        }
+    }
+
+    /// Devices, pipes and sockets, which only Unix has
+    #[cfg(unix)]
+    fn special(file_type: &FileType) -> Option<EntryType> {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_block_device() {
+            Some(EntryType::BlockDevice)
+        } else if file_type.is_char_device() {
+            Some(EntryType::CharDevice)
+        } else if file_type.is_fifo() {
+            Some(EntryType::Fifo)
+        } else if file_type.is_socket() {
+            Some(EntryType::Socket)
+        } else {
+            None
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn special(_file_type: &FileType) -> Option<EntryType> {
+        None
+    }
+
+    /// Whether an entry of `file_type`, if known, is of this kind
+    pub fn matches(&self, file_type: Option<FileType>) -> bool {
+        file_type.and_then(|t| EntryType::of(&t)).as_ref() == Some(self)
+    }
+
+    /// The letter `--type` uses
+    pub fn letter(&self) -> char {
+        match self {
+            EntryType::Dir => 'd',
+            EntryType::File => 'f',
+            EntryType::Link => 'l',
+            EntryType::BlockDevice => 'b',
+            EntryType::CharDevice => 'c',
+            EntryType::Fifo => 'p',
+            EntryType::Socket => 's',
+        }
+    }
+}
+
+#[cfg(feature = "clap")]
+impl clap::ValueEnum for EntryType {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            EntryType::Dir,
+            EntryType::File,
+            EntryType::Link,
+            EntryType::BlockDevice,
+            EntryType::CharDevice,
+            EntryType::Fifo,
+            EntryType::Socket,
+        ]
+    }
+
+    fn to_possible_value<'a>(&self) -> Option<clap::builder::PossibleValue> {
+        use clap::builder::PossibleValue;
+        Some(match self {
+            EntryType::Dir => PossibleValue::new("d").help("Directory"),
+            EntryType::File => PossibleValue::new("f").help("Regular file"),
+            EntryType::Link => PossibleValue::new("l").help("Symbolic link"),
+            EntryType::BlockDevice => PossibleValue::new("b").help("Block device"),
+            EntryType::CharDevice => PossibleValue::new("c").help("Character device"),
+            EntryType::Fifo => PossibleValue::new("p").help("Named pipe"),
+            EntryType::Socket => PossibleValue::new("s").help("Socket"),
+        })
+    }
+}